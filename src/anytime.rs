@@ -0,0 +1,172 @@
+use std::{
+    marker::PhantomData,
+    time::{Duration, Instant},
+};
+
+use crate::{
+    approximation::{Epsilon, PreparedInput},
+    context::{Context, Interrupted},
+    fft::Convoluter,
+    karmarkar_karp::karmarkar_karp,
+    solver::merge_bounds,
+};
+
+/// One step of an [`AnytimePartition`]: certified bounds on the largest subset sum not exceeding
+/// half of the total, combined over every step so far.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Refinement {
+    /// The epsilon of the approximation run in this step, `None` for the first refinement, which
+    /// only bounds the optimum between the [`karmarkar_karp`] sum and half of the total.
+    pub epsilon: Option<Epsilon>,
+    pub lower_bound: u64,
+    pub upper_bound: u64,
+    /// The time since the refinement started.
+    pub elapsed: Duration,
+}
+
+/// Approximates the partition with a coarse epsilon first, then with smaller and smaller ones
+/// until the time limit, yielding a [`Refinement`] after every step.
+///
+/// The input is prepared once and shared by every step. The first refinement comes right away,
+/// before any approximation: the [`karmarkar_karp`] sum below and half of the total above. Every
+/// later step stops at the time limit and is discarded, so a limit too short for the first
+/// epsilon still yields that coarse answer. The iteration also ends once the bounds meet or the
+/// additive error cannot shrink any further.
+pub struct AnytimePartition<T: Convoluter> {
+    prepared: PreparedInput,
+    context: Context<'static>,
+    start: Instant,
+    epsilon: Epsilon,
+    shrink: u64,
+    lower_bound: u64,
+    upper_bound: u64,
+    seeded: bool,
+    done: bool,
+    _phantom: PhantomData<T>,
+}
+
+impl<T: Convoluter> AnytimePartition<T> {
+    /// Starts the refinement at `epsilon`, rounded down to the largest `1 / k` not exceeding it
+    /// like `0.3` to `1 / 4`, see [`Epsilon::at_most`].
+    pub fn new(input: &[u16], epsilon: impl Into<Epsilon>, time_limit: Duration) -> Self {
+        let start = Instant::now();
+        let prepared = PreparedInput::new(input);
+        let heuristic = karmarkar_karp(prepared.values()).sum as u64;
+        Self {
+            context: Context::new().with_deadline(start + time_limit),
+            start,
+            epsilon: epsilon.into(),
+            shrink: 2,
+            lower_bound: heuristic,
+            upper_bound: prepared.sigma() / 2,
+            seeded: false,
+            done: false,
+            prepared,
+            _phantom: PhantomData,
+        }
+    }
+    /// The factor epsilon is divided by after every step, 2 by default.
+    pub fn with_shrink(mut self, shrink: u64) -> Self {
+        assert!(shrink >= 2);
+        self.shrink = shrink;
+        self
+    }
+    fn step(&mut self) -> Result<Refinement, Interrupted> {
+        let epsilon = self.epsilon;
+        let sumset = self
+            .prepared
+            .approximate_with_context::<T>(epsilon, &self.context)?;
+        let current = (self.lower_bound, self.upper_bound);
+        if let Some(merged) = merge_bounds(current, sumset.partition_bounds()) {
+            (self.lower_bound, self.upper_bound) = merged;
+        }
+        self.epsilon = self.epsilon.divide(self.shrink);
+        self.done = self.lower_bound >= self.upper_bound || sumset.error() <= 1;
+        Ok(self.refinement(Some(epsilon)))
+    }
+    fn refinement(&self, epsilon: Option<Epsilon>) -> Refinement {
+        Refinement {
+            epsilon,
+            lower_bound: self.lower_bound,
+            upper_bound: self.upper_bound,
+            elapsed: self.start.elapsed(),
+        }
+    }
+}
+
+impl<T: Convoluter> Iterator for AnytimePartition<T> {
+    type Item = Refinement;
+    fn next(&mut self) -> Option<Refinement> {
+        if self.done {
+            return None;
+        }
+        if !self.seeded {
+            self.seeded = true;
+            self.done = self.lower_bound >= self.upper_bound;
+            return Some(self.refinement(None));
+        }
+        let refinement = self.step().ok();
+        self.done |= refinement.is_none();
+        refinement
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use crate::{
+        fft::{Convoluter, FFT, NTT},
+        helpers::dynamic_programing_partition,
+    };
+
+    use super::AnytimePartition;
+
+    fn verify_anytime<T: Convoluter>(input: &[u16]) {
+        let optimum =
+            dynamic_programing_partition(&input.iter().map(|&x| x as u64).collect::<Vec<_>>())
+                .unwrap();
+        let refinements =
+            AnytimePartition::<T>::new(input, 0.5, Duration::from_secs(10)).collect::<Vec<_>>();
+        assert_eq!(refinements[0].epsilon, None);
+        for pair in refinements[1..].windows(2) {
+            assert!(pair[1].epsilon.unwrap().inverse() > pair[0].epsilon.unwrap().inverse());
+        }
+        for pair in refinements.windows(2) {
+            assert!(pair[0].lower_bound <= pair[1].lower_bound);
+            assert!(pair[1].upper_bound <= pair[0].upper_bound);
+        }
+        for refinement in &refinements {
+            assert!(refinement.lower_bound <= optimum && optimum <= refinement.upper_bound);
+        }
+    }
+
+    #[test]
+    fn test_anytime_fft() {
+        verify_anytime::<FFT>(&(1..=60).map(|i| i * 37 % 101 + 1).collect::<Vec<_>>());
+        // Half of the total is odd while every weight is even, so the bounds never meet.
+        verify_anytime::<FFT>(&[2, 4, 6, 8, 10, 12, 14, 16, 18, 20, 22, 24, 26, 28, 30, 34]);
+        // Values far above 1 / epsilon, where a wrong rounding base breaks the bounds.
+        verify_anytime::<FFT>(&[58795, 13966, 41074, 32670, 20202, 20658, 50218]);
+    }
+
+    #[test]
+    fn test_anytime_ntt() {
+        verify_anytime::<NTT>(&[2, 4, 6, 8, 10, 12, 14, 16, 18, 20, 22, 24, 26, 28, 30, 34]);
+        verify_anytime::<NTT>(&[31529, 39108, 64086, 30130, 49978, 59831, 64339]);
+    }
+
+    #[test]
+    fn test_anytime_time_limit() {
+        // The bounds never meet here, so only the time limit ends the refinement. A limit that
+        // has already passed stops the first approximation, after the coarse answer.
+        let input = [2, 4, 6, 8, 10, 12, 14, 16, 18, 20, 22, 24, 26, 28, 30, 34];
+        let mut refinements = AnytimePartition::<FFT>::new(&input, 0.5, Duration::ZERO);
+        let coarse = refinements.next().unwrap();
+        assert_eq!(coarse.epsilon, None);
+        assert_eq!(coarse.upper_bound, 137);
+        assert!(coarse.lower_bound <= 136);
+        assert_eq!(refinements.next(), None);
+        assert_eq!(refinements.next(), None);
+    }
+}
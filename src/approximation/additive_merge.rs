@@ -4,10 +4,11 @@ use crate::fft::Convoluter;
 use crate::helpers::*;
 use crate::subset_sum::{bounded_subset_sum, bounded_subset_sum_2d};
 
+use super::SumsetApprox;
+
 #[derive(Debug, Clone, Copy)]
 pub struct AdditiveBoundedMerger<T: Convoluter> {
     start: u64,
-    length: u64,
     t: u64,
     base: u64,
     is_2d: bool,
@@ -18,73 +19,112 @@ impl<T: Convoluter> AdditiveBoundedMerger<T> {
     pub fn new(start: u64, length: u64, delta: u64, t: u64) -> Self {
         assert!(length <= start);
         assert!(start <= t);
+        let base = ceil_div(delta, 2);
         Self {
             start,
-            length,
-            base: ceil_div(delta, 2),
+            base,
             t,
-            is_2d: fft2d_complexity(start, length, t, delta) < fft1d_complexity(t, delta),
+            is_2d: fft2d_padded_cells(start, t, base) < fft1d_padded_cells(t, base),
             _phantom: PhantomData,
         }
     }
+    /// The merged approximation, sorted and without duplicates.
     pub fn merge(&self, a: &[u64], b: &[u64]) -> Vec<u64> {
-        if a.is_empty() || b.is_empty() {
-            return vec![];
-        }
+        self.merge_sums(a.iter().copied(), b.iter().copied())
+    }
+    /// Like [`Self::merge`] for sets stored as runs, which are rounded to the base as they are
+    /// read instead of being expanded first.
+    pub fn merge_runs(&self, a: &SumsetApprox, b: &SumsetApprox) -> SumsetApprox {
+        SumsetApprox::from_sorted(self.merge_sums(a.iter(), b.iter()))
+    }
+    fn merge_sums(&self, a: impl Iterator<Item = u64>, b: impl Iterator<Item = u64>) -> Vec<u64> {
         if self.is_2d {
-            self.merge_2d(a, b)
+            self.merge_based_2d(
+                &self.based_2d_representation(a),
+                &self.based_2d_representation(b),
+            )
         } else {
-            self.merge_1d(a, b)
+            self.merge_based_1d(
+                &self.based_1d_representation(a),
+                &self.based_1d_representation(b),
+            )
         }
     }
     pub fn merge_1d(&self, a: &[u64], b: &[u64]) -> Vec<u64> {
-        let based_merged = bounded_subset_sum::<T>(
-            &self.based_1d_representation(a),
-            &self.based_1d_representation(b),
-            ceil_div(self.t, self.base) as usize * 2_usize,
-        );
+        self.merge_based_1d(
+            &self.based_1d_representation(a.iter().copied()),
+            &self.based_1d_representation(b.iter().copied()),
+        )
+    }
+    fn merge_based_1d(&self, a: &[u64], b: &[u64]) -> Vec<u64> {
+        if a.is_empty() || b.is_empty() {
+            return vec![];
+        }
+        let based_merged =
+            bounded_subset_sum::<T>(a, b, fft1d_complexity(self.t, self.base) as usize);
         self.unbased_1d_representation(&based_merged)
             .into_iter()
             .filter(|&x| x <= self.t)
             .collect()
     }
+    /// Like [`Self::merge`], but always convolves the sums as `(n / start, n % start / base)`
+    /// pairs on a two-dimensional grid. [`Self::merge`] takes this path only when the padded grid
+    /// has fewer cells than the padded one-dimensional vector.
     pub fn merge_2d(&self, a: &[u64], b: &[u64]) -> Vec<u64> {
-        let based_merged = bounded_subset_sum_2d::<T>(
-            &self.based_2d_representation(a),
-            &self.based_2d_representation(b),
-            ceil_div(self.t, self.start) as usize * 2_usize,
-            ((self.t as f64 / self.start as f64) * (self.length as f64) / self.base as f64).ceil()
-                as usize,
-        );
-        self.unbased_2d_representation(&based_merged)
+        assert!(self.start > 0);
+        self.merge_based_2d(
+            &self.based_2d_representation(a.iter().copied()),
+            &self.based_2d_representation(b.iter().copied()),
+        )
+    }
+    fn merge_based_2d(&self, a: &[(u64, u64)], b: &[(u64, u64)]) -> Vec<u64> {
+        if a.is_empty() || b.is_empty() {
+            return vec![];
+        }
+        let (x_size, y_size) = fft2d_shape(self.start, self.t, self.base);
+        let based_merged = bounded_subset_sum_2d::<T>(a, b, x_size as usize, y_size as usize);
+        // The second coordinate of a sum can exceed `start / base`, so unlike in one dimension
+        // the unbased sums need not come out in order or distinct.
+        let mut merged = self
+            .unbased_2d_representation(&based_merged)
             .into_iter()
             .filter(|&x| x <= self.t)
-            .collect()
+            .collect::<Vec<_>>();
+        merged.sort_unstable();
+        merged.dedup();
+        merged
     }
 
-    fn based_1d_representation(&self, a: &[u64]) -> Vec<u64> {
-        a.iter()
+    // Sorted input stays sorted, so the sums rounded to the same multiple are adjacent.
+    fn based_1d_representation(&self, a: impl IntoIterator<Item = u64>) -> Vec<u64> {
+        let mut based = a
+            .into_iter()
             .filter_map(|x| {
-                if *x < self.t {
+                if x < self.t {
                     Some(x / self.base)
                 } else {
                     None
                 }
             })
-            .collect()
+            .collect::<Vec<_>>();
+        based.dedup();
+        based
     }
     fn unbased_1d_representation(&self, a: &[u64]) -> Vec<u64> {
         a.iter().map(|x| x * self.base).collect()
     }
-    fn based_2d_representation(&self, a: &[u64]) -> Vec<(u64, u64)> {
-        a.iter()
-            .filter(|&&n| n <= self.t)
+    fn based_2d_representation(&self, a: impl IntoIterator<Item = u64>) -> Vec<(u64, u64)> {
+        let mut based = a
+            .into_iter()
+            .filter(|&n| n <= self.t)
             .map(|n| {
                 let x = n / self.start;
                 let y = (n - (x * self.start)) / self.base;
                 (x, y)
             })
-            .collect()
+            .collect::<Vec<_>>();
+        based.dedup();
+        based
     }
     fn unbased_2d_representation(&self, a: &[(u64, u64)]) -> Vec<u64> {
         a.iter()
@@ -93,13 +133,36 @@ impl<T: Convoluter> AdditiveBoundedMerger<T> {
     }
 }
 
-fn fft1d_complexity(t: u64, delta: u64) -> u64 {
-    ceil_div(t, delta)
+fn fft1d_complexity(t: u64, base: u64) -> u64 {
+    ceil_div(t, base).saturating_mul(2)
+}
+// The cells of both transforms once padded like the convoluters pad them: the vector to a power
+// of two, and every axis of the grid to a power of two holding the `2 * size - 1` sums along it.
+// Counts beyond a `u64` saturate, so a huge bound never makes a transform look small.
+fn fft1d_padded_cells(t: u64, base: u64) -> u64 {
+    fft1d_complexity(t, base)
+        .checked_next_power_of_two()
+        .unwrap_or(u64::MAX)
+}
+fn fft2d_padded_cells(start: u64, t: u64, base: u64) -> u64 {
+    if start == 0 {
+        return u64::MAX;
+    }
+    let (x_size, y_size) = fft2d_shape(start, t, base);
+    let padded = |size: u64| {
+        (size.saturating_mul(2) - 1)
+            .checked_next_power_of_two()
+            .unwrap_or(u64::MAX)
+    };
+    padded(x_size).saturating_mul(padded(y_size))
 }
-fn fft2d_complexity(start: u64, size: u64, t: u64, delta: u64) -> u64 {
-    let (start, size, t, delta) = (start as f64, size as f64, t as f64, delta as f64);
-    let result = ((t / start) * (t / start) * (size / delta)) as u64 + 1;
-    result
+// Both coordinates of a merged pair are sums of two coordinates, so each axis is padded to twice
+// its largest coordinate: x = n / start <= t / start and y = (n % start) / base < start / base.
+fn fft2d_shape(start: u64, t: u64, base: u64) -> (u64, u64) {
+    (
+        (t / start).saturating_mul(2).saturating_add(1),
+        (start - 1) / base * 2 + 1,
+    )
 }
 
 #[cfg(test)]
@@ -115,6 +178,7 @@ mod tests {
         let end = a.iter().chain(b.iter()).max().copied().unwrap_or(0);
         let merger = AdditiveBoundedMerger::<T>::new(start, end - start, delta, t);
         let merged = merger.merge(a, b);
+        assert!(merged.windows(2).all(|pair| pair[0] < pair[1]));
         let mut expected = vec![];
         for i in a {
             for j in b {
@@ -161,6 +225,52 @@ mod tests {
         );
     }
 
+    // Forces the two-dimensional convolution and checks it against the one-dimensional one.
+    fn verify_additive_merge_2d<T: Convoluter>(a: &[u64], b: &[u64], t: u64, delta: u64) {
+        let start = a.iter().chain(b.iter()).min().copied().unwrap_or(0);
+        let end = a.iter().chain(b.iter()).max().copied().unwrap_or(0);
+        let merger = AdditiveBoundedMerger::<T>::new(start, end - start, delta, t);
+        let merged = merger.merge_2d(a, b);
+        assert!(merged.windows(2).all(|pair| pair[0] < pair[1]));
+        let mut expected = vec![];
+        for i in a {
+            for j in b {
+                if i + j <= t {
+                    expected.push(i + j);
+                }
+            }
+        }
+        verify_approximation(&merged, &expected, 0f64, delta);
+        let merged_1d = merger.merge_1d(a, b);
+        let close = |x: u64, set: &[u64]| set.iter().any(|&y| x.abs_diff(y) <= delta);
+        assert!(merged.iter().all(|&x| close(x, &merged_1d)));
+        assert!(merged_1d.iter().all(|&x| close(x, &merged)));
+    }
+
+    #[test]
+    fn test_additive_merge_2d_fft() {
+        verify_additive_merge_2d::<FFT>(
+            &(1000..1500).collect::<Vec<_>>(),
+            &(1200..1900).collect::<Vec<_>>(),
+            20000,
+            6,
+        );
+        verify_additive_merge_2d::<FFT>(
+            &(0..400).map(|i| 3000 + i * i % 997 * 3).collect::<Vec<_>>(),
+            &(0..300).map(|i| 3500 + i * 7).collect::<Vec<_>>(),
+            9000,
+            10,
+        );
+    }
+
+    #[test]
+    fn test_additive_merge_huge_bound() {
+        // A grid of one column per sum would have more cells than a `u64` holds.
+        let merger = AdditiveBoundedMerger::<FFT>::new(1, 1, 1 << 40, u64::MAX / 2);
+        let merged = merger.merge(&[1, 5 << 40], &[3 << 40]);
+        verify_approximation(&merged, &[(3 << 40) + 1, 8 << 40], 0f64, 1 << 40);
+    }
+
     #[test]
     fn test_additive_merge_ntt() {
         verify_additive_merge::<NTT>(&[10, 12, 13], &[14, 15, 16], 10, 2);
@@ -195,4 +305,19 @@ mod tests {
             1,
         );
     }
+    #[test]
+    fn test_additive_merge_2d_ntt() {
+        verify_additive_merge_2d::<NTT>(
+            &(1000..1500).collect::<Vec<_>>(),
+            &(1200..1900).collect::<Vec<_>>(),
+            20000,
+            6,
+        );
+        verify_additive_merge_2d::<NTT>(
+            &(0..400).map(|i| 3000 + i * i % 997 * 3).collect::<Vec<_>>(),
+            &(0..300).map(|i| 3500 + i * 7).collect::<Vec<_>>(),
+            9000,
+            10,
+        );
+    }
 }
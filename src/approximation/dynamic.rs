@@ -0,0 +1,189 @@
+use std::marker::PhantomData;
+
+use crate::fft::Convoluter;
+
+use super::{merge_zero_sumsets, ApproximateSumset, Epsilon, SumsetApprox};
+
+#[derive(Clone)]
+struct Node {
+    sigma: u64,
+    sums: Vec<u64>,
+}
+
+impl Node {
+    fn leaf(item: u64) -> Self {
+        Node {
+            sigma: item,
+            sums: if item == 0 { vec![0] } else { vec![0, item] },
+        }
+    }
+}
+
+/// Keeps an approximate partition of a list of items up to date while items are removed or
+/// replaced.
+///
+/// The items are the leaves of a segment tree whose nodes hold approximate sumsets of their
+/// ranges, merged with a multiplicative error of `epsilon / (5 * height)`. An update only
+/// recomputes the `O(log n)` nodes above the changed item, and the root stays an
+/// approximation with an additive error of at most `epsilon * sigma / 5`.
+pub struct DynamicPartition<T: Convoluter> {
+    items: Vec<Option<u16>>,
+    nodes: Vec<Node>,
+    delta: Epsilon,
+    epsilon: Epsilon,
+    _phantom: PhantomData<T>,
+}
+
+impl<T: Convoluter> DynamicPartition<T> {
+    pub fn new(input: &[u16], epsilon: impl Into<Epsilon>) -> Self {
+        let epsilon = epsilon.into();
+        let capacity = input.len().next_power_of_two();
+        let height = capacity.trailing_zeros().max(1);
+        let mut nodes = vec![Node::leaf(0); 2 * capacity];
+        for (i, &item) in input.iter().enumerate() {
+            nodes[capacity + i] = Node::leaf(u64::from(item));
+        }
+        let mut partition = Self {
+            items: input.iter().copied().map(Some).collect(),
+            nodes,
+            delta: epsilon.divide(5 * height as u64),
+            epsilon,
+            _phantom: PhantomData,
+        };
+        for node in (1..capacity).rev() {
+            partition.update_node(node);
+        }
+        partition
+    }
+    fn capacity(&self) -> usize {
+        self.nodes.len() / 2
+    }
+    fn update_node(&mut self, node: usize) {
+        let (left, right) = (&self.nodes[2 * node], &self.nodes[2 * node + 1]);
+        let sigma = left.sigma + right.sigma;
+        let sums = merge_zero_sumsets::<T>(&left.sums, &right.sums, sigma, self.delta);
+        self.nodes[node] = Node { sigma, sums };
+    }
+    fn set_leaf(&mut self, index: usize, item: Option<u16>) {
+        self.items[index] = item;
+        let mut node = self.capacity() + index;
+        self.nodes[node] = Node::leaf(item.map_or(0, u64::from));
+        while node > 1 {
+            node /= 2;
+            self.update_node(node);
+        }
+    }
+    /// The items by their original index, `None` for removed ones.
+    pub fn items(&self) -> &[Option<u16>] {
+        &self.items
+    }
+    /// Removes the item at `index` and returns it, if it was still present. An `index` past the
+    /// original items changes nothing and returns `None`.
+    pub fn remove(&mut self, index: usize) -> Option<u16> {
+        let item = self.items.get(index).copied().flatten();
+        if item.is_some() {
+            self.set_leaf(index, None);
+        }
+        item
+    }
+    /// Puts `item` at `index`, replacing any item that is still there, and returns what was there
+    /// before: `Some(None)` if that item had been removed. An `index` past the original items
+    /// changes nothing and returns `None`.
+    pub fn replace(&mut self, index: usize, item: u16) -> Option<Option<u16>> {
+        let previous = *self.items.get(index)?;
+        self.set_leaf(index, Some(item));
+        Some(previous)
+    }
+    pub fn sigma(&self) -> u64 {
+        self.root().sigma
+    }
+    fn root(&self) -> &Node {
+        &self.nodes[1]
+    }
+    pub fn sumset(&self) -> ApproximateSumset {
+        let root = self.root();
+        ApproximateSumset::from_parts(
+            SumsetApprox::from_sorted(root.sums.iter().copied()),
+            root.sigma,
+            self.epsilon,
+        )
+    }
+    pub fn partition(&self) -> f64 {
+        self.sumset().partition()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        fft::{FFT, NTT},
+        helpers::{dynamic_programing_partition, naive_sumset, test::verify_approximation},
+    };
+
+    use super::*;
+
+    fn verify_current<T: Convoluter>(partition: &DynamicPartition<T>, epsilon: f64) {
+        let present = partition
+            .items()
+            .iter()
+            .flatten()
+            .copied()
+            .map(u64::from)
+            .collect::<Vec<_>>();
+        let sumset = partition.sumset();
+        assert_eq!(sumset.sigma(), present.iter().sum::<u64>());
+        verify_approximation(
+            &sumset.sums().to_vec(),
+            &[naive_sumset(&present), vec![0]].concat(),
+            0.0,
+            sumset.error(),
+        );
+        let t = sumset.sigma() / 2;
+        let opt = dynamic_programing_partition(&present).unwrap();
+        assert!(opt as f64 - partition.partition() <= epsilon * t as f64);
+    }
+
+    fn verify_dynamic<T: Convoluter>(input: &[u16], epsilon: f64) {
+        let mut partition = DynamicPartition::<T>::new(input, epsilon);
+        verify_current(&partition, epsilon);
+        for index in (0..input.len()).step_by(3) {
+            assert_eq!(partition.remove(index), Some(input[index]));
+            assert_eq!(partition.remove(index), None);
+            verify_current(&partition, epsilon);
+        }
+        assert_eq!(partition.replace(0, 12345), Some(None));
+        verify_current(&partition, epsilon);
+        let len = input.len();
+        assert_eq!(partition.remove(len), None);
+        assert_eq!(partition.replace(len, 7), None);
+        assert_eq!(partition.items().len(), len);
+        verify_current(&partition, epsilon);
+    }
+
+    #[test]
+    fn test_dynamic_partition_fft() {
+        verify_dynamic::<FFT>(
+            &[1001, 2000, 3000, 4000, 5000, 1000, 1001, 1002, 5, 77],
+            0.1,
+        );
+        verify_dynamic::<FFT>(&[500], 0.1);
+    }
+
+    #[test]
+    fn test_dynamic_partition_ntt() {
+        verify_dynamic::<NTT>(
+            &[1001, 2000, 3000, 4000, 5000, 1000, 1001, 1002, 5, 77],
+            0.1,
+        );
+        verify_dynamic::<NTT>(&[500], 0.1);
+    }
+
+    #[test]
+    fn test_dynamic_partition_empty() {
+        let mut partition = DynamicPartition::<FFT>::new(&[], 0.1);
+        assert_eq!(partition.remove(0), None);
+        assert_eq!(partition.replace(0, 1), None);
+        assert_eq!(partition.sigma(), 0);
+        assert_eq!(partition.partition(), 0.0);
+    }
+}
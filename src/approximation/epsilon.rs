@@ -0,0 +1,89 @@
+/// An approximation parameter of the form `1 / k`, so that everything derived from it is exact
+/// integer arithmetic and does not depend on floating point rounding.
+///
+/// Functions taking an `impl Into<Epsilon>` accept any `f64` in `(0, 1]` as well, rounded down to
+/// the largest `1 / k` not exceeding it, which keeps every guarantee stated for the `f64`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Epsilon {
+    inverse: u64,
+}
+
+impl Epsilon {
+    pub fn from_inverse(inverse: u64) -> Self {
+        Self::try_from_inverse(inverse).expect("the inverse of epsilon is at least 1")
+    }
+    /// `1 / inverse`, or `None` if `inverse` is 0.
+    pub fn try_from_inverse(inverse: u64) -> Option<Self> {
+        (inverse >= 1).then_some(Self { inverse })
+    }
+    /// The largest `1 / k` that is at most `epsilon`. An `epsilon` within rounding error of some
+    /// `1 / k`, like `1.0 / 49.0`, is taken to be exactly that.
+    pub fn at_most(epsilon: f64) -> Self {
+        Self::try_at_most(epsilon).unwrap_or_else(|| panic!("{epsilon}"))
+    }
+    /// Like [`Self::at_most`], or `None` if `epsilon` is not in `(0, 1]`.
+    pub fn try_at_most(epsilon: f64) -> Option<Self> {
+        if !(0.0 < epsilon && epsilon <= 1.0) {
+            return None;
+        }
+        let nearest = (1.0 / epsilon).round();
+        let inverse = if (nearest * epsilon - 1.0).abs() <= 4.0 * f64::EPSILON {
+            nearest
+        } else {
+            (1.0 / epsilon).ceil()
+        };
+        Some(Self {
+            inverse: inverse as u64,
+        })
+    }
+    /// The `k` of `1 / k`.
+    pub fn inverse(self) -> u64 {
+        self.inverse
+    }
+    pub fn value(self) -> f64 {
+        1.0 / self.inverse as f64
+    }
+    /// `epsilon / divisor`.
+    pub fn divide(self, divisor: u64) -> Self {
+        Self::from_inverse(self.inverse * divisor)
+    }
+    /// `floor(epsilon * x / divisor)`.
+    pub fn floor_mul(self, x: u64, divisor: u64) -> u64 {
+        (x as u128 / (self.inverse as u128 * divisor as u128)) as u64
+    }
+    /// `ceil(epsilon * x / divisor)`.
+    pub fn ceil_mul(self, x: u64, divisor: u64) -> u64 {
+        (x as u128).div_ceil(self.inverse as u128 * divisor as u128) as u64
+    }
+}
+
+impl From<f64> for Epsilon {
+    fn from(epsilon: f64) -> Self {
+        Self::at_most(epsilon)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Epsilon;
+
+    #[test]
+    fn test_epsilon() {
+        assert_eq!(Epsilon::at_most(0.1).inverse(), 10);
+        assert_eq!(Epsilon::at_most(0.3).inverse(), 4);
+        assert_eq!(Epsilon::at_most(1.0).inverse(), 1);
+        for k in 1..1000 {
+            assert_eq!(Epsilon::at_most(1.0 / k as f64).inverse(), k);
+        }
+        let epsilon = Epsilon::from_inverse(4);
+        assert_eq!(epsilon.floor_mul(10, 1), 2);
+        assert_eq!(epsilon.ceil_mul(10, 1), 3);
+        assert_eq!(epsilon.ceil_mul(u64::MAX, 1), u64::MAX / 4 + 1);
+        assert_eq!(epsilon.divide(3).floor_mul(25, 2), 1);
+        assert_eq!(Epsilon::try_at_most(0.5), Some(Epsilon::from_inverse(2)));
+        for epsilon in [0.0, -1.0, 2.0, f64::NAN] {
+            assert_eq!(Epsilon::try_at_most(epsilon), None);
+        }
+        assert_eq!(Epsilon::try_from_inverse(0), None);
+    }
+}
@@ -0,0 +1,171 @@
+use std::marker::PhantomData;
+
+use crate::fft::Convoluter;
+
+use super::{
+    merge_approximation_runs, merge_zero_sumsets, ApproximateSumset, Epsilon, SumsetApprox,
+};
+
+struct Block {
+    sigma: u64,
+    sums: Vec<u64>,
+}
+
+/// Maintains an approximate sumset while items are added.
+///
+/// Items are kept in blocks of `2^k` items, like the digits of a binary counter, so adding an
+/// item carries through at most `log n` block merges. A merge into level `k + 1` loses a factor
+/// of `1 - epsilon / (10 * (k + 1)^2)`, which keeps every block a `(1 - epsilon / 6)`-approximation
+/// no matter how many items arrive. Queries combine the `log n` blocks on demand.
+pub struct IncrementalSumset<T: Convoluter> {
+    epsilon: Epsilon,
+    blocks: Vec<Option<Block>>,
+    sigma: u64,
+    len: usize,
+    _phantom: PhantomData<T>,
+}
+
+impl<T: Convoluter> IncrementalSumset<T> {
+    pub fn new(epsilon: impl Into<Epsilon>) -> Self {
+        Self {
+            epsilon: epsilon.into(),
+            blocks: vec![],
+            sigma: 0,
+            len: 0,
+            _phantom: PhantomData,
+        }
+    }
+    pub fn len(&self) -> usize {
+        self.len
+    }
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+    pub fn sigma(&self) -> u64 {
+        self.sigma
+    }
+    pub fn push(&mut self, item: u16) {
+        let item = u64::from(item);
+        let mut carry = Block {
+            sigma: item,
+            sums: if item == 0 { vec![0] } else { vec![0, item] },
+        };
+        let mut level = 0;
+        loop {
+            if level == self.blocks.len() {
+                self.blocks.push(None);
+            }
+            match self.blocks[level].take() {
+                Some(block) => {
+                    carry = self.merge_blocks(&block, &carry, level);
+                    level += 1;
+                }
+                None => {
+                    self.blocks[level] = Some(carry);
+                    break;
+                }
+            }
+        }
+        self.sigma += item;
+        self.len += 1;
+    }
+    fn merge_blocks(&self, a: &Block, b: &Block, level: usize) -> Block {
+        let sigma = a.sigma + b.sigma;
+        let delta = self
+            .epsilon
+            .divide(10 * (level as u64 + 1) * (level as u64 + 1));
+        Block {
+            sigma,
+            sums: merge_zero_sumsets::<T>(&a.sums, &b.sums, sigma, delta),
+        }
+    }
+    /// Combines the blocks into an approximation of the sumset of all items added so far. The
+    /// blocks contribute at most `epsilon * sigma / 6` of error and the rounding of the
+    /// combination at most `epsilon * sigma / 16`.
+    pub fn sumset(&self) -> ApproximateSumset {
+        let blocks = self.blocks.iter().flatten().collect::<Vec<_>>();
+        let base = self
+            .epsilon
+            .floor_mul(self.sigma, 16 * blocks.len().max(1) as u64)
+            .max(1);
+        let based = blocks
+            .iter()
+            .map(|block| block.sums.iter().map(|x| x / base).collect::<Vec<_>>())
+            .collect::<Vec<_>>();
+        let sums = SumsetApprox::from_sorted(
+            merge_approximation_runs::<T>(&based)
+                .iter()
+                .map(|x| x * base),
+        )
+        .union(&SumsetApprox::from_sorted([0]));
+        ApproximateSumset::from_parts(sums, self.sigma, self.epsilon)
+    }
+    pub fn partition(&self) -> f64 {
+        self.sumset().partition()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        fft::{FFT, NTT},
+        helpers::{dynamic_programing_partition, naive_sumset, test::verify_approximation},
+    };
+
+    use super::*;
+
+    fn verify_incremental<T: Convoluter>(input: &[u16], epsilon: f64) {
+        let mut sumset = IncrementalSumset::<T>::new(epsilon);
+        for (i, &item) in input.iter().enumerate() {
+            sumset.push(item);
+            let prefix = input[..=i]
+                .iter()
+                .copied()
+                .map(u64::from)
+                .collect::<Vec<_>>();
+            let approximation = sumset.sumset();
+            verify_approximation(
+                &approximation.sums().to_vec(),
+                &[naive_sumset(&prefix), vec![0]].concat(),
+                0.0,
+                approximation.error(),
+            );
+        }
+    }
+
+    fn verify_incremental_partition<T: Convoluter>(input: &[u16], epsilon: f64) {
+        let mut sumset = IncrementalSumset::<T>::new(epsilon);
+        for (i, &item) in input.iter().enumerate() {
+            sumset.push(item);
+            if i % 10 != 0 {
+                continue;
+            }
+            let prefix = input[..=i]
+                .iter()
+                .copied()
+                .map(u64::from)
+                .collect::<Vec<_>>();
+            let t = prefix.iter().sum::<u64>() / 2;
+            let opt = dynamic_programing_partition(&prefix).unwrap();
+            assert!(opt as f64 - sumset.partition() <= epsilon * t as f64);
+        }
+    }
+
+    #[test]
+    fn test_incremental_fft() {
+        verify_incremental::<FFT>(&[1001, 2000, 3000, 0, 4000, 5000, 1000, 1001, 1002, 5], 0.1);
+        verify_incremental_partition::<FFT>(
+            &(0..30).map(|x| 1000 + x * x * 7).collect::<Vec<_>>(),
+            0.1,
+        );
+    }
+
+    #[test]
+    fn test_incremental_ntt() {
+        verify_incremental::<NTT>(&[1001, 2000, 3000, 0, 4000, 5000, 1000, 1001, 1002, 5], 0.1);
+        verify_incremental_partition::<NTT>(
+            &(0..30).map(|x| 1000 + x * x * 7).collect::<Vec<_>>(),
+            0.1,
+        );
+    }
+}
@@ -1,26 +1,30 @@
 use std::marker::PhantomData;
 
-use crate::{fft::Convoluter, helpers::naive_sumset};
+use crate::{
+    context::{Context, Interrupted},
+    fft::Convoluter,
+    helpers::naive_sumset,
+};
 
-use super::MultiplicativeBoundedMerger;
+use super::{Epsilon, MultiplicativeBoundedMerger, SumsetApprox};
 
 pub enum SumsetEpsilonAdditiveAproximation {}
 
 impl SumsetEpsilonAdditiveAproximation {
     #[allow(clippy::new_ret_no_self)]
     pub fn new<T: Convoluter>(epsilon_inv: u64) -> SumsetIntervalApproximation<T> {
-        SumsetIntervalApproximation::new(epsilon_inv, 1.0 / (epsilon_inv as f64))
+        SumsetIntervalApproximation::new(epsilon_inv, Epsilon::from_inverse(epsilon_inv))
     }
 }
 
 pub struct SumsetIntervalApproximation<T: Convoluter> {
     start: u64,
-    delta: f64,
+    delta: Epsilon,
     _phantom: PhantomData<T>,
 }
 
 impl<T: Convoluter> SumsetIntervalApproximation<T> {
-    pub fn new(start: u64, delta: f64) -> Self {
+    pub fn new(start: u64, delta: Epsilon) -> Self {
         Self {
             start,
             delta,
@@ -28,8 +32,19 @@ impl<T: Convoluter> SumsetIntervalApproximation<T> {
         }
     }
     pub fn approximate(&self, set: &[u64]) -> Vec<u64> {
+        self.approximate_with_context(set, &Context::new())
+            .expect("the context never interrupts")
+    }
+    /// Like [`Self::approximate`], but checks `context` between merges and reports them.
+    pub fn approximate_with_context(
+        &self,
+        set: &[u64],
+        context: &Context,
+    ) -> Result<Vec<u64>, Interrupted> {
         let n = set.len();
-        let delta = self.delta / (n as f64).log2().ceil();
+        // `ceil(log2(n))` levels of merges, each with its share of the error.
+        let levels = n.next_power_of_two().trailing_zeros().max(1);
+        let delta = self.delta.divide(levels as u64);
         for x in set {
             assert!(
                 self.start <= *x && *x <= self.start * 2,
@@ -38,19 +53,26 @@ impl<T: Convoluter> SumsetIntervalApproximation<T> {
                 x
             )
         }
-        let result = self.approximate_recursive(set, delta);
-        result
+        Ok(self.approximate_recursive(set, delta, context)?.0.to_vec())
     }
-    fn approximate_recursive(&self, a: &[u64], delta: f64) -> Vec<u64> {
+    // Also returns the height of the merge tree.
+    fn approximate_recursive(
+        &self,
+        a: &[u64],
+        delta: Epsilon,
+        context: &Context,
+    ) -> Result<(SumsetApprox, u32), Interrupted> {
+        context.check()?;
         if a.len() <= 10 {
-            return naive_sumset(a);
+            return Ok((naive_sumset(a).into(), 0));
         }
         let length = a.len();
         let pivot = length / 2;
         let (left, right) = a.split_at(pivot);
 
-        let left_approximation = self.approximate_recursive(left, delta);
-        let right_approximation = self.approximate_recursive(right, delta);
+        let (left_approximation, left_level) = self.approximate_recursive(left, delta, context)?;
+        let (right_approximation, right_level) =
+            self.approximate_recursive(right, delta, context)?;
 
         let merger = MultiplicativeBoundedMerger::<T>::new(
             self.start,
@@ -58,12 +80,16 @@ impl<T: Convoluter> SumsetIntervalApproximation<T> {
             delta,
             length as u64 * self.start * 2,
         );
-        [
-            merger.merge(&left_approximation, &right_approximation),
-            left_approximation,
-            right_approximation,
-        ]
-        .concat()
+        let merged =
+            merger.merge_runs_with_context(&left_approximation, &right_approximation, context)?;
+        let level = left_level.max(right_level) + 1;
+        context.merged(level);
+        Ok((
+            merged
+                .union(&left_approximation)
+                .union(&right_approximation),
+            level,
+        ))
     }
 }
 
@@ -80,7 +106,8 @@ mod tests {
         let start = set.iter().min().copied().unwrap_or(0);
         let end = set.iter().max().copied().unwrap_or(0);
         assert!(start * 2 >= end);
-        let approximation = SumsetIntervalApproximation::<T>::new(start, delta).approximate(&set);
+        let approximation =
+            SumsetIntervalApproximation::<T>::new(start, Epsilon::at_most(delta)).approximate(&set);
         helpers::test::verify_approximation(&approximation, &naive_sumset(&set), delta, 0);
     }
 
@@ -1,57 +1,169 @@
-use std::{collections::HashMap, ops::Add};
+use std::{
+    collections::{BTreeMap, HashMap},
+    ops::Add,
+};
 
-use crate::{fft::Convoluter, helpers::reduce_multiplicity, subset_sum::subset_sum};
+use crate::{
+    context::{Context, Interrupted},
+    fft::Convoluter,
+    helpers::{count_multiplicities, reduce_multiplicities},
+};
 
 pub mod additive_merge;
+pub mod dynamic;
+pub mod epsilon;
+pub mod incremental;
 pub mod interval;
 pub mod multiplicative_merge;
+pub mod query;
+pub mod repeated;
+pub mod subset_sum_ratio;
+pub mod sumset_approx;
+pub mod unbounded;
+pub mod witness;
 
 #[cfg(test)]
 mod tests;
 
 pub use additive_merge::AdditiveBoundedMerger;
+pub use dynamic::DynamicPartition;
+pub use epsilon::Epsilon;
+pub use incremental::IncrementalSumset;
 pub use interval::{SumsetEpsilonAdditiveAproximation, SumsetIntervalApproximation};
 pub use multiplicative_merge::MultiplicativeBoundedMerger;
+pub use query::{ApproximateSumset, SumsetAnswer};
+pub use repeated::{
+    approximate_bounded_copies_sumset, approximate_multiset_sumset,
+    approximate_multiset_sumset_with_context,
+};
+pub use subset_sum_ratio::{approximate_subset_sum_ratio, SubsetSumRatio};
+pub use sumset_approx::{Run, SumsetApprox};
+pub use unbounded::approximate_unbounded_sumset;
+pub use witness::{
+    approximate_choice, approximate_subset, multiple_choice_subset_sum, Choice, Side,
+};
 
-pub fn approximate_sumset<T: Convoluter>(input: &[u16], epsilon: f64) -> Vec<u64> {
-    let input = input.iter().copied().map(u64::from).collect::<Vec<u64>>();
+pub fn approximate_sumset<T: Convoluter>(input: &[u16], epsilon: impl Into<Epsilon>) -> Vec<u64> {
+    approximate_sumset_runs::<T>(input, epsilon).to_vec()
+}
+
+pub fn approximate_sumset_runs<T: Convoluter>(
+    input: &[u16],
+    epsilon: impl Into<Epsilon>,
+) -> SumsetApprox {
+    approximate_sumset_with_context::<T>(input, epsilon, &Context::new())
+        .expect("the context never interrupts")
+}
+
+/// Like [`approximate_sumset_runs`], but checks `context` between merges, stopping once it is
+/// cancelled or past its deadline, and reports every merge to it.
+pub fn approximate_sumset_with_context<T: Convoluter>(
+    input: &[u16],
+    epsilon: impl Into<Epsilon>,
+    context: &Context,
+) -> Result<SumsetApprox, Interrupted> {
+    approximate_prepared_sumset::<T>(&PreparedInput::new(input), epsilon.into(), context)
+}
+
+/// The part of [`approximate_sumset_with_context`] that does not depend on epsilon: the widened
+/// values, their total and their multiplicity classes. Preparing an input once lets runs with
+/// several epsilons share it. Every run rounds, splits into intervals and reduces the classes
+/// rather than the single items, so only `O(d)` work per epsilon remains for `d` distinct values.
+#[derive(Debug, Clone)]
+pub struct PreparedInput {
+    values: Vec<u64>,
+    classes: Vec<(u64, usize)>,
+    sigma: u64,
+}
+
+impl PreparedInput {
+    pub fn new(input: &[u16]) -> Self {
+        let values = input.iter().copied().map(u64::from).collect::<Vec<u64>>();
+        let classes = count_multiplicities(&values).into_iter().collect();
+        let sigma = values.iter().sum();
+        Self {
+            values,
+            classes,
+            sigma,
+        }
+    }
+    pub fn values(&self) -> &[u64] {
+        &self.values
+    }
+    pub fn sigma(&self) -> u64 {
+        self.sigma
+    }
+    pub fn approximate_sumset<T: Convoluter>(
+        &self,
+        epsilon: impl Into<Epsilon>,
+    ) -> ApproximateSumset {
+        self.approximate_with_context::<T>(epsilon, &Context::new())
+            .expect("the context never interrupts")
+    }
+    /// Like [`approximate_sumset_with_context`], wrapped for queries.
+    pub fn approximate_with_context<T: Convoluter>(
+        &self,
+        epsilon: impl Into<Epsilon>,
+        context: &Context,
+    ) -> Result<ApproximateSumset, Interrupted> {
+        let epsilon = epsilon.into();
+        let sums = approximate_prepared_sumset::<T>(self, epsilon, context)?;
+        Ok(ApproximateSumset::from_parts(sums, self.sigma, epsilon))
+    }
+}
+
+fn approximate_prepared_sumset<T: Convoluter>(
+    prepared: &PreparedInput,
+    epsilon: Epsilon,
+    context: &Context,
+) -> Result<SumsetApprox, Interrupted> {
+    let input = &prepared.values;
     if input.is_empty() {
-        return vec![0];
+        return Ok(SumsetApprox::from_sorted([0]));
     }
     if input.len() == 1 {
-        return vec![input[0], 0];
+        return Ok(SumsetApprox::from_sorted([0, input[0]]));
     }
     let n = input.len();
-    let eps_prim = epsilon / ((n as f64 / epsilon).log2() + 1f64);
-    let eps_div_eps_prim = (epsilon / eps_prim).ceil() as u64;
-    let epsilon = eps_div_eps_prim as f64 * eps_prim;
-    let eps_inv = (1.0 / epsilon).ceil() as u64;
-    let eps_prim_inv = eps_inv * eps_div_eps_prim;
-    let _epsilon = 1.0 / (eps_inv as f64);
-    let eps_prim = 1.0 / (eps_prim_inv as f64);
-    let sigma: u64 = input.iter().sum();
-    let _t = sigma / 2;
-
-    let base = (sigma as f64 / (100f64 * n as f64 * eps_inv as f64)).ceil() as u64;
-    let y_set = input
+    let classes = &prepared.classes;
+    // With d distinct values the multiset path needs d merges of size about d / epsilon,
+    // which beats the general merge tree once d * d <= n.
+    if classes.len() * classes.len() <= n {
+        return approximate_multiset_sumset_with_context::<T>(classes, epsilon, context);
+    }
+    let eps_inv = epsilon.inverse();
+    // epsilon' = epsilon / (log2(n / epsilon) + 1), rounded down to 1 / (eps_inv * levels).
+    let eps_div_eps_prim = (n as u64 * eps_inv).next_power_of_two().trailing_zeros() as u64 + 1;
+    let eps_prim = epsilon.divide(eps_div_eps_prim);
+    let eps_prim_inv = eps_prim.inverse();
+    let sigma = prepared.sigma;
+
+    let base = Epsilon::from_inverse(n as u64 * eps_inv).ceil_mul(sigma, 100);
+    // The rounded classes, with the multiplicity of every value.
+    let y_classes = classes
         .iter()
-        .map(|&i| i / base)
-        .filter(|&x| x != 0)
+        .map(|&(value, mult)| (value / base, mult))
+        .filter(|&(y, _)| y != 0)
         .collect::<Vec<_>>();
-    let scale = (100 * eps_inv).div_ceil(*y_set.iter().min().unwrap());
-    let y_set = y_set.into_iter().map(|x| x * scale).collect::<Vec<_>>();
+    let scale = (100 * eps_inv).div_ceil(y_classes.iter().map(|&(y, _)| y).min().unwrap());
 
-    let sigma = sigma * scale;
-    let _t = sigma * 2;
+    // The total of the rounded, scaled values, the unit every later step works in. Deriving
+    // the second rounding base from the original total instead would be `base` times too coarse.
+    let sigma = y_classes
+        .iter()
+        .map(|&(y, mult)| y * scale * mult as u64)
+        .sum::<u64>();
 
     let z_range_start = 100 * eps_inv;
 
-    let z_set = y_set
-        .iter()
-        .map(|&x| ElementApproximation::new(z_range_start, x))
-        .collect::<Vec<_>>();
+    let mut z_classes = BTreeMap::new();
+    for &(y, mult) in &y_classes {
+        *z_classes
+            .entry(ElementApproximation::new(z_range_start, y * scale))
+            .or_default() += mult;
+    }
 
-    let z_set_prim = reduce_multiplicity(&z_set);
+    let z_set_prim = reduce_multiplicities(&z_classes);
     let mut partition: HashMap<(u32, bool), Vec<u64>> = HashMap::new();
 
     for (el, &mult) in z_set_prim.iter() {
@@ -66,7 +178,7 @@ pub fn approximate_sumset<T: Convoluter>(input: &[u16], epsilon: f64) -> Vec<u64
 
     let eps_inv_for_approx = eps_prim_inv * 100;
 
-    let base_2 = (eps_prim * sigma as f64 / 100_f64).ceil() as u64;
+    let base_2 = eps_prim.ceil_mul(sigma, 100);
 
     let a_js = partition
         .into_iter()
@@ -76,44 +188,83 @@ pub fn approximate_sumset<T: Convoluter>(input: &[u16], epsilon: f64) -> Vec<u64
             }
             let scaled = v.iter().map(|&x| x * eps_div_eps_prim).collect::<Vec<_>>();
 
-            let result = SumsetEpsilonAdditiveAproximation::new::<T>(eps_inv_for_approx)
-                .approximate(&scaled)
-                .into_iter()
-                .map(|x| x * 2_u64.pow(k) / eps_div_eps_prim)
-                .map(|x| x / base_2)
-                .collect::<Vec<_>>();
-            result
+            Ok(
+                SumsetEpsilonAdditiveAproximation::new::<T>(eps_inv_for_approx)
+                    .approximate_with_context(&scaled, context)?
+                    .into_iter()
+                    .map(|x| x * 2_u64.pow(k) / eps_div_eps_prim)
+                    .map(|x| x / base_2)
+                    .collect::<Vec<_>>(),
+            )
         })
-        .collect::<Vec<_>>();
+        .collect::<Result<Vec<_>, _>>()?;
 
-    let mut merged = merge_approximations::<T>(&a_js);
+    // Rescaling is monotone, so the merged runs stay sorted.
+    let merged = SumsetApprox::from_sorted(
+        merge_approximation_runs_with_context::<T>(&a_js, context)?
+            .0
+            .iter()
+            .map(|el| el * base_2 / scale * base),
+    );
 
-    for el in &mut merged {
-        *el = *el * base_2 / scale * base;
-    }
+    Ok(merged.union(&SumsetApprox::from_sorted([0])))
+}
 
-    if !merged.contains(&0) {
-        merged.push(0);
-    }
+pub fn merge_approximations<T: Convoluter>(a_js: &[Vec<u64>]) -> Vec<u64> {
+    merge_approximation_runs::<T>(a_js).to_vec()
+}
 
-    merged.sort();
+/// Like [`merge_approximations`], but checks `context` between merges and reports them.
+pub fn merge_approximations_with_context<T: Convoluter>(
+    a_js: &[Vec<u64>],
+    context: &Context,
+) -> Result<Vec<u64>, Interrupted> {
+    Ok(merge_approximation_runs_with_context::<T>(a_js, context)?
+        .0
+        .to_vec())
+}
 
-    merged
+fn merge_approximation_runs<T: Convoluter>(a_js: &[Vec<u64>]) -> SumsetApprox {
+    merge_approximation_runs_with_context::<T>(a_js, &Context::new())
+        .expect("the context never interrupts")
+        .0
 }
 
-pub fn merge_approximations<T: Convoluter>(a_js: &[Vec<u64>]) -> Vec<u64> {
+// Also returns the height of the merge tree.
+fn merge_approximation_runs_with_context<T: Convoluter>(
+    a_js: &[Vec<u64>],
+    context: &Context,
+) -> Result<(SumsetApprox, u32), Interrupted> {
+    context.check()?;
     if a_js.is_empty() {
-        return vec![];
+        return Ok((SumsetApprox::default(), 0));
     } else if a_js.len() == 1 {
-        return a_js[0].clone();
+        return Ok((SumsetApprox::from(a_js[0].clone()), 0));
     }
     let (left, right) = a_js.split_at(a_js.len() / 2);
-    let (left, right) = (
-        merge_approximations::<T>(left),
-        merge_approximations::<T>(right),
+    let ((left, left_level), (right, right_level)) = (
+        merge_approximation_runs_with_context::<T>(left, context)?,
+        merge_approximation_runs_with_context::<T>(right, context)?,
     );
+    context.check()?;
 
-    [subset_sum::<T>(&left, &right), left, right].concat()
+    let merged = left.merge::<T>(&right).union(&left).union(&right);
+    let level = left_level.max(right_level) + 1;
+    context.merged(level);
+    Ok((merged, level))
+}
+
+/// Merges two sumsets that both contain 0 and sum to at most `sigma` with a multiplicative
+/// error of `delta`. Since `a + b` already covers `a` and `b`, only 0 itself has to be added
+/// back, it falls below the smallest interval of the merger.
+fn merge_zero_sumsets<T: Convoluter>(a: &[u64], b: &[u64], sigma: u64, delta: Epsilon) -> Vec<u64> {
+    if sigma == 0 {
+        return vec![0];
+    }
+    let merged = MultiplicativeBoundedMerger::<T>::new(1, 1, delta, sigma).merge(a, b);
+    SumsetApprox::from(merged)
+        .union(&SumsetApprox::from_sorted([0]))
+        .to_vec()
 }
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
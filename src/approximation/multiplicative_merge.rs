@@ -1,22 +1,23 @@
-use std::{collections::HashSet, marker::PhantomData};
+use std::marker::PhantomData;
 
 use crate::{
+    context::{Context, Interrupted},
     fft::Convoluter,
     helpers::{ceil_div, PowerOfTwoIterator},
 };
 
-use super::AdditiveBoundedMerger;
+use super::{AdditiveBoundedMerger, Epsilon, SumsetApprox};
 
 pub struct MultiplicativeBoundedMerger<T: Convoluter> {
     start: u64,
     length: u64,
-    delta: f64,
+    delta: Epsilon,
     t: u64,
     _phantom: PhantomData<T>,
 }
 
 impl<T: Convoluter> MultiplicativeBoundedMerger<T> {
-    pub fn new(start: u64, length: u64, delta: f64, t: u64) -> Self {
+    pub fn new(start: u64, length: u64, delta: Epsilon, t: u64) -> Self {
         assert!(length <= start);
         assert!(start <= t);
         Self {
@@ -27,26 +28,47 @@ impl<T: Convoluter> MultiplicativeBoundedMerger<T> {
             _phantom: PhantomData,
         }
     }
+    /// The merged approximation, sorted and without duplicates.
     pub fn merge(&self, a: &[u64], b: &[u64]) -> Vec<u64> {
+        self.merge_with_context(a, b, &Context::new())
+            .expect("the context never interrupts")
+    }
+    /// Like [`Self::merge`], but checks `context` before every interval.
+    pub fn merge_with_context(
+        &self,
+        a: &[u64],
+        b: &[u64],
+        context: &Context,
+    ) -> Result<Vec<u64>, Interrupted> {
+        let (a, b) = (
+            a.iter().copied().collect::<SumsetApprox>(),
+            b.iter().copied().collect::<SumsetApprox>(),
+        );
+        Ok(self.merge_runs_with_context(&a, &b, context)?.to_vec())
+    }
+    /// Like [`Self::merge_with_context`] for sets stored as runs, which stay in that form.
+    pub fn merge_runs_with_context(
+        &self,
+        a: &SumsetApprox,
+        b: &SumsetApprox,
+        context: &Context,
+    ) -> Result<SumsetApprox, Interrupted> {
+        let mut result = SumsetApprox::default();
         if a.is_empty() || b.is_empty() {
-            return vec![];
+            return Ok(result);
         }
-        let mut result = HashSet::new();
         for r in PowerOfTwoIterator::new(ceil_div(self.start, 6), self.t) {
+            context.check()?;
             let merged = self.merge_interval(a, b, r);
-            result.extend(merged);
+            result = result.union(&merged);
         }
-        result.into_iter().collect()
+        Ok(result)
     }
-    fn merge_interval(&self, a: &[u64], b: &[u64], r: u64) -> Vec<u64> {
-        let additive_delta = (self.delta * r as f64).ceil() as u64;
+    fn merge_interval(&self, a: &SumsetApprox, b: &SumsetApprox, r: u64) -> SumsetApprox {
+        let additive_delta = self.delta.ceil_mul(r, 1);
         let merger =
             AdditiveBoundedMerger::<T>::new(self.start, self.length, additive_delta, 6 * r);
-        merger
-            .merge(a, b)
-            .into_iter()
-            .filter(|&x| x >= r && x <= self.t)
-            .collect()
+        merger.merge_runs(a, b).clamped(r, self.t)
     }
 }
 
@@ -62,8 +84,10 @@ mod tests {
     fn verify_multiplicative_merge<T: Convoluter>(a: &[u64], b: &[u64], t: u64, delta: f64) {
         let start = a.iter().chain(b.iter()).min().copied().unwrap_or(0);
         let end = a.iter().chain(b.iter()).max().copied().unwrap_or(0);
-        let merger = MultiplicativeBoundedMerger::<T>::new(start, end - start, delta, t);
+        let merger =
+            MultiplicativeBoundedMerger::<T>::new(start, end - start, Epsilon::at_most(delta), t);
         let merged = merger.merge(a, b);
+        assert!(merged.windows(2).all(|pair| pair[0] < pair[1]));
         let mut expected = vec![];
         for i in a {
             for j in b {
@@ -0,0 +1,179 @@
+use std::ops::RangeInclusive;
+
+use crate::fft::Convoluter;
+
+use super::{approximate_sumset_runs, Epsilon, SumsetApprox};
+
+/// An approximate sum together with its guarantee: some subset of the input sums to a value
+/// in `value..=value + error`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SumsetAnswer {
+    pub value: u64,
+    pub error: u64,
+}
+
+impl SumsetAnswer {
+    pub fn reachable(&self) -> RangeInclusive<u64> {
+        self.value..=self.value + self.error
+    }
+}
+
+/// An approximate sumset built once per instance and queried for many targets.
+///
+/// Every approximate sum has a reachable sum at most `error` above it, and every reachable sum
+/// has an approximate sum at most `error` below it.
+#[derive(Debug, Clone)]
+pub struct ApproximateSumset {
+    sums: SumsetApprox,
+    sigma: u64,
+    epsilon: Epsilon,
+    error: u64,
+}
+
+impl ApproximateSumset {
+    pub fn new<T: Convoluter>(input: &[u16], epsilon: impl Into<Epsilon>) -> Self {
+        let epsilon = epsilon.into();
+        let sigma = input.iter().copied().map(u64::from).sum();
+        Self::from_parts(approximate_sumset_runs::<T>(input, epsilon), sigma, epsilon)
+    }
+    /// Wraps sums that approximate a sumset of total `sigma` with error `epsilon * sigma / 4`.
+    pub fn from_parts(sums: SumsetApprox, sigma: u64, epsilon: impl Into<Epsilon>) -> Self {
+        let epsilon = epsilon.into();
+        Self {
+            sums,
+            sigma,
+            epsilon,
+            error: epsilon.ceil_mul(sigma, 4),
+        }
+    }
+    pub fn sums(&self) -> &SumsetApprox {
+        &self.sums
+    }
+    pub fn sigma(&self) -> u64 {
+        self.sigma
+    }
+    pub fn epsilon(&self) -> Epsilon {
+        self.epsilon
+    }
+    pub fn error(&self) -> u64 {
+        self.error
+    }
+    fn answer(&self, value: u64) -> SumsetAnswer {
+        SumsetAnswer {
+            value,
+            error: self.error,
+        }
+    }
+    /// The largest approximate sum at most `t`. No reachable sum at most `t` exceeds
+    /// `value + error`.
+    pub fn nearest_below(&self, t: u64) -> Option<SumsetAnswer> {
+        self.sums.max_at_most(t).map(|value| self.answer(value))
+    }
+    /// The smallest approximate sum at least `t`. No reachable sum at least `t + error` is
+    /// below `value`.
+    pub fn nearest_above(&self, t: u64) -> Option<SumsetAnswer> {
+        self.sums.min_at_least(t).map(|value| self.answer(value))
+    }
+    /// Whether `t` is close to a reachable sum. This holds whenever a reachable sum lies within
+    /// `error` of `t`, and never when none lies within `2 * error`.
+    pub fn is_near(&self, t: u64) -> bool {
+        self.sums
+            .min_at_least(t.saturating_sub(2 * self.error))
+            .is_some_and(|value| value <= t + self.error)
+    }
+    /// Certified bounds on the largest reachable sum at most half of the total: a reachable sum
+    /// is at least the lower bound, and none at most half of the total exceeds the upper bound.
+    pub fn partition_bounds(&self) -> (u64, u64) {
+        // Every approximate sum `a` has a reachable sum in `[a, a + error]`, so `a <= t - error`
+        // certifies a sum of at least `a`, and no reachable sum up to `t` exceeds the largest
+        // `a <= t` by more than `error`.
+        let t = self.sigma / 2;
+        let lower_bound = t
+            .checked_sub(self.error)
+            .and_then(|below| self.nearest_below(below))
+            .map_or(0, |answer| answer.value);
+        let upper_bound = self
+            .nearest_below(t)
+            .map_or(t, |answer| t.min(answer.value + self.error));
+        (lower_bound, upper_bound)
+    }
+    pub fn partition(&self) -> f64 {
+        self.partition_value() as f64
+    }
+    /// [`Self::partition`] in exact integer arithmetic.
+    pub fn partition_value(&self) -> u64 {
+        let t = self.sigma / 2;
+        let a = self.nearest_below(t).map_or(0, |answer| answer.value);
+        let compilment = t - self.epsilon.floor_mul(t, 2);
+        a.min(compilment)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        fft::{FFT, NTT},
+        helpers::naive_sumset,
+    };
+
+    use super::*;
+
+    fn verify_queries<T: Convoluter>(input: &[u16], epsilon: f64) {
+        let sumset = ApproximateSumset::new::<T>(input, epsilon);
+        let mut reachable = naive_sumset(&input.iter().copied().map(u64::from).collect::<Vec<_>>());
+        reachable.push(0);
+        let error = sumset.error();
+        let optimum = reachable
+            .iter()
+            .copied()
+            .filter(|&x| x <= sumset.sigma() / 2)
+            .max()
+            .unwrap();
+        let (lower_bound, upper_bound) = sumset.partition_bounds();
+        assert!(lower_bound <= optimum && optimum <= upper_bound);
+        let has_reachable =
+            |range: RangeInclusive<u64>| reachable.iter().any(|x| range.contains(x));
+        for t in (0..=sumset.sigma() + 10).step_by(7) {
+            let best_below = reachable.iter().copied().filter(|&x| x <= t).max().unwrap();
+            let below = sumset.nearest_below(t).unwrap();
+            assert!(below.value <= t && has_reachable(below.reachable()));
+            assert!(best_below <= below.value + error);
+
+            if let Some(above) = sumset.nearest_above(t) {
+                assert!(above.value >= t && has_reachable(above.reachable()));
+                assert!(reachable.iter().all(|&x| x < t + error || x >= above.value));
+            } else {
+                assert!(reachable.iter().all(|&x| x < t + error));
+            }
+
+            if has_reachable(t.saturating_sub(error)..=t + error) {
+                assert!(sumset.is_near(t));
+            }
+            if sumset.is_near(t) {
+                assert!(has_reachable(t.saturating_sub(2 * error)..=t + 2 * error));
+            }
+        }
+    }
+
+    #[test]
+    fn test_queries_fft() {
+        verify_queries::<FFT>(&[], 0.1);
+        verify_queries::<FFT>(&[1000], 0.1);
+        verify_queries::<FFT>(&[1001, 2000, 3000, 4000, 5000, 1000, 1001, 1002, 5], 0.05);
+        verify_queries::<FFT>(&[7, 7, 7, 7, 7, 7, 7, 7, 7, 3000], 0.01);
+        // Coarse epsilons round values far above 1, where the error is easy to get wrong.
+        verify_queries::<FFT>(&[31529, 39108, 64086, 30130, 49978, 59831, 64339], 0.5);
+        verify_queries::<FFT>(&[58795, 13966, 41074, 32670, 20202, 20658, 50218], 0.5);
+    }
+
+    #[test]
+    fn test_queries_ntt() {
+        verify_queries::<NTT>(&[], 0.1);
+        verify_queries::<NTT>(&[1000], 0.1);
+        verify_queries::<NTT>(&[1001, 2000, 3000, 4000, 5000, 1000, 1001, 1002, 5], 0.05);
+        verify_queries::<NTT>(&[7, 7, 7, 7, 7, 7, 7, 7, 7, 3000], 0.01);
+        // Coarse epsilons round values far above 1, where the error is easy to get wrong.
+        verify_queries::<NTT>(&[31529, 39108, 64086, 30130, 49978, 59831, 64339], 0.5);
+        verify_queries::<NTT>(&[58795, 13966, 41074, 32670, 20202, 20658, 50218], 0.5);
+    }
+}
@@ -0,0 +1,143 @@
+use crate::{
+    context::{Context, Interrupted},
+    fft::Convoluter,
+    subset_sum::subset_sum,
+};
+
+use super::{merge_approximation_runs_with_context, Epsilon, SumsetApprox};
+
+/// Approximates the sumset of a multiset given as `(value, multiplicity)` classes. The sums of
+/// a class with multiplicity `c` are built by repeated squaring in `O(log c)` merges of size
+/// `O(d * log c / epsilon)`, within an additive error of `base`. The classes are then rounded
+/// down to multiples of `base` and merged, so with `d` classes the additive error stays below
+/// `2 * d * base <= epsilon * sigma / 4`, and those merges never exceed `8 * d / epsilon`.
+pub fn approximate_multiset_sumset<T: Convoluter>(
+    classes: &[(u64, usize)],
+    epsilon: impl Into<Epsilon>,
+) -> SumsetApprox {
+    approximate_multiset_sumset_with_context::<T>(classes, epsilon, &Context::new())
+        .expect("the context never interrupts")
+}
+
+/// Like [`approximate_multiset_sumset`], but checks `context` before every squaring and between
+/// the merges of the classes.
+pub fn approximate_multiset_sumset_with_context<T: Convoluter>(
+    classes: &[(u64, usize)],
+    epsilon: impl Into<Epsilon>,
+    context: &Context,
+) -> Result<SumsetApprox, Interrupted> {
+    let sigma: u64 = classes
+        .iter()
+        .map(|&(value, mult)| value * mult as u64)
+        .sum();
+    approximate_classes::<T>(classes, sigma, epsilon.into(), context)
+}
+
+// Approximates the sumset of the classes within an additive error of `epsilon * scale / 4`,
+// dropping the sums above `scale`, which the error is measured against.
+fn approximate_classes<T: Convoluter>(
+    classes: &[(u64, usize)],
+    scale: u64,
+    epsilon: Epsilon,
+    context: &Context,
+) -> Result<SumsetApprox, Interrupted> {
+    let classes = classes
+        .iter()
+        .copied()
+        .filter(|&(value, mult)| value != 0 && mult != 0)
+        .collect::<Vec<_>>();
+    if classes.is_empty() {
+        return Ok(SumsetApprox::from_sorted([0]));
+    }
+    let base = epsilon.floor_mul(scale, 8 * classes.len() as u64).max(1);
+
+    let copies = classes
+        .iter()
+        .map(|&(value, mult)| {
+            let mut copies = approximate_copies::<T>(value, mult as u64, base, context)?
+                .into_iter()
+                .take_while(|&x| x <= scale)
+                .map(|x| x / base)
+                .collect::<Vec<_>>();
+            copies.dedup();
+            Ok(copies)
+        })
+        .collect::<Result<Vec<_>, _>>()?;
+
+    Ok(SumsetApprox::from_sorted(
+        merge_approximation_runs_with_context::<T>(&copies, context)?
+            .0
+            .iter()
+            .map(|x| x * base),
+    ))
+}
+
+/// Approximates the sums up to `t` that use each `value` at most `copies` times, for
+/// `(value, copies)` item types.
+///
+/// Like [`approximate_multiset_sumset`], but the rounding base is derived from `t` instead of the
+/// total, so the additive error is `epsilon * t / 4` however far the copies reach beyond `t`.
+/// Copies that alone exceed `t` can never help, so every class keeps at most `t / value` of
+/// them, and its sums above `t` are dropped before the classes are merged. Every sum up to `t`
+/// has an approximation within that error below it, and every approximation has a sum within
+/// that error above it, which may itself lie slightly above `t`.
+pub fn approximate_bounded_copies_sumset<T: Convoluter>(
+    classes: &[(u64, usize)],
+    t: u64,
+    epsilon: impl Into<Epsilon>,
+) -> SumsetApprox {
+    let classes = classes
+        .iter()
+        .filter(|&&(value, _)| value != 0 && value <= t)
+        .map(|&(value, copies)| (value, copies.min((t / value) as usize)))
+        .collect::<Vec<_>>();
+    SumsetApprox::from_sorted(
+        approximate_classes::<T>(&classes, t, epsilon.into(), &Context::new())
+            .expect("the context never interrupts")
+            .iter()
+            .take_while(|&x| x <= t),
+    )
+}
+
+/// Approximates the sums `0, v, ..., c * v` of `c` copies of `v` within an additive `error`,
+/// following the binary digits of `c`: the sums of `m` copies are squared into those of `2 * m`,
+/// and `v` is added when the digit is set. After every step the sums are rounded down to a grid
+/// proportional to `m`. An error made at `m` copies is at most doubled `log(c / m)` times
+/// afterwards, so the grid `error * m / (4 * steps * c)` keeps the total below `error`, while
+/// every convolution has the same size of about `4 * steps * c * v / error`.
+fn approximate_copies<T: Convoluter>(
+    value: u64,
+    mult: u64,
+    error: u64,
+    context: &Context,
+) -> Result<Vec<u64>, Interrupted> {
+    let digits = 64 - mult.leading_zeros();
+    let steps = 2 * digits as u128;
+    let grid = |m: u64| (error as u128 * m as u128 / (4 * steps * mult as u128)).max(1) as u64;
+    let round = |sums: &mut Vec<u64>, grid: u64| {
+        sums.iter_mut().for_each(|x| *x = *x / grid * grid);
+        sums.dedup();
+    };
+    let mut sums = vec![0, value];
+    let mut m = 1;
+    for digit in (0..digits - 1).rev() {
+        context.check()?;
+        // Rounding both operands costs at most two grid steps.
+        m *= 2;
+        let step = grid(m);
+        let mut indices = sums.iter().map(|x| x / step).collect::<Vec<_>>();
+        indices.dedup();
+        sums = subset_sum::<T>(&indices, &indices)
+            .into_iter()
+            .map(|x| x * step)
+            .collect();
+        if mult >> digit & 1 == 1 {
+            m += 1;
+            let added = sums.iter().map(|x| x + value).collect::<Vec<_>>();
+            sums.extend(added);
+            sums.sort_unstable();
+            round(&mut sums, grid(m));
+        }
+    }
+    Ok(sums)
+}
@@ -0,0 +1,357 @@
+use std::{cmp::Ordering, collections::HashMap};
+
+use crate::{
+    approximation::{approximate_choice, Epsilon, Side},
+    equal_subset_sum::pigeonhole_equal_subsets,
+    fft::Convoluter,
+    solver::SolverError,
+};
+
+/// Two disjoint, non-empty subsets of the input, given by item indices.
+#[derive(Debug, Clone, PartialEq)]
+pub struct SubsetSumRatio {
+    pub first: Vec<usize>,
+    pub second: Vec<usize>,
+    pub first_sum: u64,
+    pub second_sum: u64,
+    /// The larger sum divided by the smaller one.
+    pub ratio: f64,
+}
+
+impl SubsetSumRatio {
+    fn new(input: &[u64], mut first: Vec<usize>, mut second: Vec<usize>) -> Self {
+        first.sort_unstable();
+        second.sort_unstable();
+        let first_sum: u64 = first.iter().map(|&i| input[i]).sum();
+        let second_sum: u64 = second.iter().map(|&i| input[i]).sum();
+        SubsetSumRatio {
+            ratio: first_sum.max(second_sum) as f64 / first_sum.min(second_sum) as f64,
+            first,
+            second,
+            first_sum,
+            second_sum,
+        }
+    }
+}
+
+#[derive(Clone, Copy)]
+struct State {
+    total: u64,
+    // The items of each side as bits over the positions in the sorted order.
+    first: u64,
+    second: u64,
+}
+
+/// The most non-zero items [`approximate_subset_sum_ratio`] accepts, so that both sides of a state
+/// fit in a `u64` bit mask each.
+pub const SUBSET_SUM_RATIO_ITEMS: usize = 64;
+
+/// Finds two disjoint, non-empty subsets whose sums have a ratio at most `1 + epsilon` times the
+/// best possible. Items equal to 0 are never used. Returns `Ok(None)` with fewer than two non-zero
+/// items.
+///
+/// Inputs that must contain two equal sums by the pigeonhole principle are answered with those
+/// right away. Otherwise every item `w_p` in turn is taken as the largest item of the first side,
+/// and the smaller items become groups `{0, w, 2w}` of [`approximate_choice`], for the first side,
+/// neither side and the second side. A total near the sum of the smaller items plus `w_p` is a
+/// pair of sides whose sums differ by little, and the nearest one is found up to
+/// `epsilon * w_p / 8`. When some pair with this largest item differs by at most
+/// `epsilon * w_p / 4`, the pair found has a ratio of at most `1 + epsilon`, which is good enough
+/// whatever the best ratio is, so the search stops there. Some largest item has such a pair
+/// whenever the items up to some `w_p` sum to at least `32 * w_p / epsilon`: then some range
+/// `(y / 2, y]` holds at least `16 / epsilon` items, and two of their subsets differ by less than
+/// `epsilon * y / 8`, as do the subsets left once the items they share are removed.
+///
+/// Only inputs that are sparse in this sense get past the sumset search, to a dynamic program.
+/// For every item `j`, taken as the largest item of the solution, the smaller items are rounded
+/// down to multiples of `epsilon * w_j / (8n)`. The dynamic program over the rounded difference of
+/// the two sums keeps, for every difference, the pair with the largest real total, since a larger
+/// total means a better ratio at the same difference. The rounding shifts a difference by at most
+/// `epsilon * w_j / 8`, while the smaller side of a solution worth improving is at least
+/// `w_j / 2`.
+///
+/// The sumset search merges `O(n^2)` groups with an error of `epsilon / (8n)`. A layer of the
+/// dynamic program holds `O(n^2 / epsilon)` differences and every `j` runs through `j` layers, so
+/// it takes `O(n^4 / epsilon)` time. Only the current layer is kept, with the sides of every state
+/// as bit masks, which needs `O(n^2 / epsilon)` memory. Fails with more than
+/// [`SUBSET_SUM_RATIO_ITEMS`] non-zero items unless the pigeonhole principle applies.
+pub fn approximate_subset_sum_ratio<T: Convoluter>(
+    input: &[u64],
+    epsilon: impl Into<Epsilon>,
+) -> Result<Option<SubsetSumRatio>, SolverError> {
+    let epsilon = epsilon.into();
+    let mut order = (0..input.len())
+        .filter(|&i| input[i] != 0)
+        .collect::<Vec<_>>();
+    // Zeros would only give a pair of empty sums, so the shortcut never sees them.
+    let weights = order.iter().map(|&i| input[i]).collect::<Vec<_>>();
+    if let Some(equal) = pigeonhole_equal_subsets(&weights) {
+        let side = |items: Vec<usize>| items.into_iter().map(|i| order[i]).collect();
+        return Ok(Some(SubsetSumRatio::new(
+            input,
+            side(equal.first),
+            side(equal.second),
+        )));
+    }
+    order.sort_by_key(|&i| input[i]);
+    let n = order.len();
+    if n > SUBSET_SUM_RATIO_ITEMS {
+        return Err(SolverError::TooManyItems {
+            items: n,
+            max: SUBSET_SUM_RATIO_ITEMS,
+        });
+    }
+    // Any ratio of at most `1 + epsilon` is good enough.
+    let k = epsilon.inverse() as u128;
+    let good_enough = |pair: &SubsetSumRatio| {
+        let (max, min) = (
+            pair.first_sum.max(pair.second_sum),
+            pair.first_sum.min(pair.second_sum),
+        );
+        max as u128 * k <= min as u128 * (k + 1)
+    };
+    let mut near = vec![];
+    for pair in (1..n).filter_map(|p| near_equal_sides::<T>(input, &order[..=p], epsilon)) {
+        if good_enough(&pair) {
+            return Ok(Some(pair));
+        }
+        near.push(pair);
+    }
+    Ok((1..n)
+        .filter_map(|j| rounded_difference_sides(input, &order, j, epsilon))
+        .chain(near)
+        .min_by(compare_ratios))
+}
+
+// The pair of sides, with the last of the sorted `items` on the first one, whose sums differ the
+// least up to `epsilon * w_p / 8`. `None` if that leaves the second side empty, or if the sums
+// are too large for the mergers.
+fn near_equal_sides<T: Convoluter>(
+    input: &[u64],
+    items: &[usize],
+    epsilon: Epsilon,
+) -> Option<SubsetSumRatio> {
+    let (&largest, smaller) = items.split_last()?;
+    let w_p = input[largest];
+    let groups = smaller
+        .iter()
+        .map(|&i| vec![0, input[i], 2 * input[i]])
+        .collect::<Vec<_>>();
+    // A total of `sigma + w_p + d` stands for sides whose sums differ by `d`. The mergers reach up
+    // to 12 times the target, beyond that only the dynamic program applies.
+    let target = smaller
+        .iter()
+        .try_fold(w_p, |sum, &i| sum.checked_add(input[i]))?;
+    target.checked_mul(12)?;
+    let epsilon = epsilon.divide(8 * target.div_ceil(w_p));
+    let choice = approximate_choice::<T>(&groups, target, epsilon, Side::Nearest)?;
+    let (mut first, mut second) = (vec![largest], vec![]);
+    for (&i, &pick) in smaller.iter().zip(&choice.picks) {
+        match pick {
+            0 => first.push(i),
+            2 => second.push(i),
+            _ => {}
+        }
+    }
+    (!second.is_empty()).then(|| SubsetSumRatio::new(input, first, second))
+}
+
+// The best pair of the dynamic program over rounded differences with `order[j]` as its largest
+// item.
+fn rounded_difference_sides(
+    input: &[u64],
+    order: &[usize],
+    j: usize,
+    epsilon: Epsilon,
+) -> Option<SubsetSumRatio> {
+    let n = order.len();
+    let delta = epsilon.floor_mul(input[order[j]], 8 * n as u64).max(1);
+    let rounded = |i: usize| (input[order[i]] / delta) as i64;
+    let mut layer: HashMap<(i64, bool), State> = HashMap::from([(
+        (rounded(j), false),
+        State {
+            total: input[order[j]],
+            first: 1 << j,
+            second: 0,
+        },
+    )]);
+    for i in 0..j {
+        let mut next: HashMap<(i64, bool), State> = HashMap::new();
+        for (&(difference, nonempty), state) in &layer {
+            let w = input[order[i]];
+            let options = [
+                ((difference, nonempty), *state),
+                (
+                    (difference + rounded(i), nonempty),
+                    State {
+                        total: state.total + w,
+                        first: state.first | 1 << i,
+                        ..*state
+                    },
+                ),
+                (
+                    (difference - rounded(i), true),
+                    State {
+                        total: state.total + w,
+                        second: state.second | 1 << i,
+                        ..*state
+                    },
+                ),
+            ];
+            for (key, state) in options {
+                let entry = next.entry(key).or_insert(state);
+                if entry.total < state.total {
+                    *entry = state;
+                }
+            }
+        }
+        layer = next;
+    }
+    layer
+        .iter()
+        .filter(|(&(_, nonempty), _)| nonempty)
+        .map(|(_, state)| witness(input, order, state))
+        .min_by(compare_ratios)
+}
+
+// Compares the ratios of two solutions exactly, by cross-multiplying their sums.
+fn compare_ratios(a: &SubsetSumRatio, b: &SubsetSumRatio) -> Ordering {
+    let (a_max, a_min) = (a.first_sum.max(a.second_sum), a.first_sum.min(a.second_sum));
+    let (b_max, b_min) = (b.first_sum.max(b.second_sum), b.first_sum.min(b.second_sum));
+    (a_max as u128 * b_min as u128).cmp(&(b_max as u128 * a_min as u128))
+}
+
+fn witness(input: &[u64], order: &[usize], state: &State) -> SubsetSumRatio {
+    let side = |mask: u64| {
+        (0..order.len())
+            .filter(|&i| mask >> i & 1 == 1)
+            .map(|i| order[i])
+            .collect()
+    };
+    SubsetSumRatio::new(input, side(state.first), side(state.second))
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::fft::{FFT, NTT};
+
+    use super::*;
+
+    fn best_ratio(input: &[u64]) -> f64 {
+        let mut best = f64::INFINITY;
+        let mut sides = vec![0; input.len()];
+        loop {
+            let sum = |side| -> u64 {
+                (0..input.len())
+                    .filter(|&i| sides[i] == side)
+                    .map(|i| input[i])
+                    .sum()
+            };
+            let (a, b) = (sum(1), sum(2));
+            if a > 0 && b > 0 {
+                best = best.min(a.max(b) as f64 / a.min(b) as f64);
+            }
+            let Some(i) = sides.iter().position(|&side| side != 2) else {
+                return best;
+            };
+            sides[i] += 1;
+            sides[..i].fill(0);
+        }
+    }
+
+    fn verify_subset_sum_ratio<T: Convoluter>(input: &[u64], epsilon: f64) {
+        let result = approximate_subset_sum_ratio::<T>(input, epsilon)
+            .unwrap()
+            .unwrap();
+        assert!(result.first.iter().all(|i| !result.second.contains(i)));
+        assert_eq!(
+            result.first_sum,
+            result.first.iter().map(|&i| input[i]).sum()
+        );
+        assert_eq!(
+            result.second_sum,
+            result.second.iter().map(|&i| input[i]).sum()
+        );
+        assert!(result.first_sum > 0 && result.second_sum > 0);
+        let best = best_ratio(input);
+        assert!(result.ratio <= best * (1.0 + epsilon), "{result:?} {best}");
+    }
+
+    fn verify_subset_sum_ratios<T: Convoluter>() {
+        verify_subset_sum_ratio::<T>(&[1, 2, 3], 0.1);
+        verify_subset_sum_ratio::<T>(&[1000, 2001, 3004, 4007, 5011, 6013], 0.01);
+        verify_subset_sum_ratio::<T>(&[1000, 2001, 3004, 4007, 5011, 6013], 0.5);
+        verify_subset_sum_ratio::<T>(&[1, 10, 100, 1000, 10000, 0, 100000], 0.01);
+        verify_subset_sum_ratio::<T>(&[65535, 65521, 65519, 65497, 65479, 65449, 7], 0.001);
+        verify_subset_sum_ratio::<T>(&[7, 7], 0.1);
+        verify_subset_sum_ratio::<T>(&[0, 0, 1, 2, 3, 4, 5, 6, 7, 8, 9], 0.1);
+        verify_subset_sum_ratio::<T>(&[3, 5, 17, 40, 97, 250], 0.05);
+    }
+
+    #[test]
+    fn test_subset_sum_ratio_fft() {
+        verify_subset_sum_ratios::<FFT>();
+    }
+
+    #[test]
+    fn test_subset_sum_ratio_ntt() {
+        verify_subset_sum_ratios::<NTT>();
+    }
+
+    #[test]
+    fn test_near_equal_sides() {
+        // The sides 40 and 31 + 10 differ by 1.
+        let input = [31, 55, 10, 40];
+        let mut order = (0..input.len()).collect::<Vec<_>>();
+        order.sort_by_key(|&i| input[i]);
+        let epsilon = Epsilon::from_inverse(2);
+        let near = near_equal_sides::<FFT>(&input, &order[..3], epsilon).unwrap();
+        assert_eq!((near.first, near.second), (vec![3], vec![0, 2]));
+        // Every pair with 10 as the largest item leaves the second side empty.
+        assert_eq!(near_equal_sides::<FFT>(&input, &order[..1], epsilon), None);
+    }
+
+    #[test]
+    fn test_subset_sum_ratio_item_limit() {
+        // Powers of two have no equal sums, so the pigeonhole shortcut does not apply.
+        let input = (0..63).map(|i| 1 << i).collect::<Vec<_>>();
+        assert!(approximate_subset_sum_ratio::<FFT>(&input, 0.5).is_ok());
+        let input = (0..63)
+            .map(|i| 1 << i)
+            .chain((1..8).map(|i| (1 << 62) + i))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            approximate_subset_sum_ratio::<FFT>(&input, 0.5),
+            Err(SolverError::TooManyItems { items: 70, max: 64 })
+        );
+        let input = (0..100).map(|i| 200 + i).collect::<Vec<_>>();
+        let result = approximate_subset_sum_ratio::<FFT>(&input, 0.5)
+            .unwrap()
+            .unwrap();
+        assert_eq!(result.first_sum, result.second_sum);
+        // Zeros do not keep the shortcut from finding the pair among the other items.
+        let input = (200..300).chain([0, 0]).collect::<Vec<_>>();
+        let result = approximate_subset_sum_ratio::<FFT>(&input, 0.5)
+            .unwrap()
+            .unwrap();
+        assert!(result.first_sum > 0);
+        assert_eq!(result.first_sum, result.second_sum);
+        assert_eq!(
+            result.first_sum,
+            result.first.iter().map(|&i| input[i]).sum::<u64>()
+        );
+        assert_eq!(
+            result.second_sum,
+            result.second.iter().map(|&i| input[i]).sum::<u64>()
+        );
+    }
+
+    #[test]
+    fn test_subset_sum_ratio_too_few_items() {
+        assert_eq!(
+            approximate_subset_sum_ratio::<FFT>(&[0, 5, 0], 0.1),
+            Ok(None)
+        );
+        assert_eq!(approximate_subset_sum_ratio::<FFT>(&[], 0.1), Ok(None));
+    }
+}
@@ -0,0 +1,362 @@
+use crate::{fft::Convoluter, helpers::gcd};
+
+/// An arithmetic run of sums `start, start + step, ..., end`. A run holding a single sum has
+/// `start == end` and `step == 1`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Run {
+    pub start: u64,
+    pub end: u64,
+    pub step: u64,
+}
+
+impl Run {
+    fn single(value: u64) -> Self {
+        Run {
+            start: value,
+            end: value,
+            step: 1,
+        }
+    }
+    pub fn iter(&self) -> impl Iterator<Item = u64> {
+        (self.start..=self.end).step_by(self.step as usize)
+    }
+    fn max_at_most(&self, t: u64) -> Option<u64> {
+        if t < self.start {
+            return None;
+        }
+        let t = t.min(self.end);
+        Some(t - (t - self.start) % self.step)
+    }
+    fn min_at_least(&self, t: u64) -> Option<u64> {
+        if t > self.end {
+            return None;
+        }
+        let t = t.max(self.start);
+        Some(t + (self.step - (t - self.start) % self.step) % self.step)
+    }
+}
+
+/// A sorted, deduplicated set of sums stored as arithmetic runs. Approximate sumsets are mostly
+/// multiples of a rounding base, so long runs collapse into a single entry.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct SumsetApprox {
+    runs: Vec<Run>,
+    len: usize,
+}
+
+impl SumsetApprox {
+    /// Builds the set from ascending values, duplicates are skipped.
+    pub fn from_sorted(values: impl IntoIterator<Item = u64>) -> Self {
+        let mut set = SumsetApprox::default();
+        for value in values {
+            set.push(value);
+        }
+        set
+    }
+    fn push(&mut self, value: u64) {
+        if let Some(run) = self.runs.last_mut() {
+            assert!(run.end <= value, "values must be sorted");
+            if run.end == value {
+                return;
+            }
+            if run.start == run.end {
+                run.step = value - run.start;
+                run.end = value;
+            } else if value - run.end == run.step {
+                run.end = value;
+            } else {
+                self.runs.push(Run::single(value));
+            }
+        } else {
+            self.runs.push(Run::single(value));
+        }
+        self.len += 1;
+    }
+    // Appends the sums `start, start + step, ..., end`, none of them below the current maximum,
+    // extending the last run when it continues with the same step.
+    fn push_run(&mut self, start: u64, end: u64, step: u64) {
+        self.push(start);
+        if start == end {
+            return;
+        }
+        // The last run ends at `start` now, whether it was pushed or already there.
+        let last = self.runs.last_mut().unwrap();
+        if last.start == last.end {
+            last.step = step;
+            last.end = end;
+        } else if last.step == step {
+            last.end = end;
+        } else if start + step == end {
+            self.runs.push(Run::single(end));
+        } else {
+            self.runs.push(Run {
+                start: start + step,
+                end,
+                step,
+            });
+        }
+        self.len += ((end - start) / step) as usize;
+    }
+    pub fn runs(&self) -> &[Run] {
+        &self.runs
+    }
+    pub fn len(&self) -> usize {
+        self.len
+    }
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+    pub fn iter(&self) -> impl Iterator<Item = u64> + '_ {
+        self.runs.iter().flat_map(Run::iter)
+    }
+    pub fn to_vec(&self) -> Vec<u64> {
+        self.iter().collect()
+    }
+    pub fn min(&self) -> Option<u64> {
+        self.runs.first().map(|run| run.start)
+    }
+    pub fn max(&self) -> Option<u64> {
+        self.runs.last().map(|run| run.end)
+    }
+    /// The sums in `low..=high`, cut from the runs without visiting the sums.
+    pub fn clamped(&self, low: u64, high: u64) -> SumsetApprox {
+        let mut clamped = SumsetApprox::default();
+        for run in &self.runs {
+            if let (Some(start), Some(end)) = (run.min_at_least(low), run.max_at_most(high)) {
+                if start <= end {
+                    clamped.push_run(start, end, run.step);
+                }
+            }
+        }
+        clamped
+    }
+    /// The union of both sets, in a single pass over their runs. Where one set has no sums
+    /// between two sums of the other, whole runs are copied at once, so only the sums where the
+    /// sets interleave are visited one by one.
+    pub fn union(&self, other: &SumsetApprox) -> SumsetApprox {
+        let (mut a, mut b) = (RunCursor::new(&self.runs), RunCursor::new(&other.runs));
+        let mut union = SumsetApprox::default();
+        loop {
+            let (low, high) = match (a.peek(), b.peek()) {
+                (Some(x), Some(y)) if x <= y => (&mut a, Some(y)),
+                (Some(x), Some(_)) => (&mut b, Some(x)),
+                (Some(_), None) => (&mut a, None),
+                (None, Some(_)) => (&mut b, None),
+                (None, None) => break,
+            };
+            let (start, end, step) = low.take_at_most(high.unwrap_or(u64::MAX));
+            union.push_run(start, end, step);
+        }
+        union
+    }
+    /// The sumset `self + other`, computed with a single convolution. Both sets are shifted to
+    /// start at 0 and divided by the largest step that divides the gaps of every run of both, so
+    /// a set of multiples of a rounding base is convolved at the size of its rounded values.
+    /// Every run is written into the characteristic vector as one strided block, and the sums
+    /// are read back from it into runs.
+    pub fn merge<T: Convoluter>(&self, other: &SumsetApprox) -> SumsetApprox {
+        let (Some(min), Some(other_min)) = (self.min(), other.min()) else {
+            return SumsetApprox::default();
+        };
+        // A few pairs are cheaper to add up than a convolution over their span.
+        if self.len().saturating_mul(other.len()) < 1000 {
+            return self
+                .iter()
+                .flat_map(|x| other.iter().map(move |y| x + y))
+                .collect();
+        }
+        let step = gcd(self.lattice_step(), other.lattice_step()).max(1);
+        let encode = |set: &SumsetApprox, min: u64| {
+            let mut encoded = vec![false; ((set.max().unwrap() - min) / step) as usize + 1];
+            for run in &set.runs {
+                let (first, last) = ((run.start - min) / step, (run.end - min) / step);
+                let stride = (run.step / step).max(1) as usize;
+                for i in (first as usize..=last as usize).step_by(stride) {
+                    encoded[i] = true;
+                }
+            }
+            encoded
+        };
+        let (a, b) = (encode(self, min), encode(other, other_min));
+        let size = a.len() + b.len() - 1;
+        let sums = T::new(size).convolute_characteristic_vecs(&a, &b);
+        SumsetApprox::from_sorted(
+            sums.iter()
+                .enumerate()
+                .filter(|&(_, &reachable)| reachable)
+                .map(|(i, _)| min + other_min + i as u64 * step),
+        )
+    }
+    // The largest step dividing the distance of every sum from the smallest one, 0 for a
+    // single sum.
+    fn lattice_step(&self) -> u64 {
+        let min = self.min().unwrap_or(0);
+        self.runs.iter().fold(0, |step, run| {
+            let run_step = if run.start == run.end { 0 } else { run.step };
+            gcd(gcd(step, run.start - min), run_step)
+        })
+    }
+    pub fn contains(&self, value: u64) -> bool {
+        self.max_at_most(value) == Some(value)
+    }
+    /// The largest sum that is at most `t`.
+    pub fn max_at_most(&self, t: u64) -> Option<u64> {
+        let index = self.runs.partition_point(|run| run.start <= t);
+        index
+            .checked_sub(1)
+            .and_then(|index| self.runs[index].max_at_most(t))
+    }
+    /// The smallest sum that is at least `t`.
+    pub fn min_at_least(&self, t: u64) -> Option<u64> {
+        let index = self.runs.partition_point(|run| run.end < t);
+        self.runs.get(index).and_then(|run| run.min_at_least(t))
+    }
+}
+
+// Reads a list of runs front to back, a part of a run at a time.
+struct RunCursor<'a> {
+    runs: &'a [Run],
+    // The smallest sum of `runs[0]` not read yet.
+    next: u64,
+}
+
+impl<'a> RunCursor<'a> {
+    fn new(runs: &'a [Run]) -> Self {
+        let next = runs.first().map_or(0, |run| run.start);
+        RunCursor { runs, next }
+    }
+    fn peek(&self) -> Option<u64> {
+        (!self.runs.is_empty()).then_some(self.next)
+    }
+    // Reads the sums of the current run from the next one up to `t`, which is at least the next
+    // one, and returns them as `(start, end, step)`.
+    fn take_at_most(&mut self, t: u64) -> (u64, u64, u64) {
+        let (run, start) = (self.runs[0], self.next);
+        let end = Run { start, ..run }.max_at_most(t).unwrap();
+        if end == run.end {
+            self.runs = &self.runs[1..];
+            self.next = self.runs.first().map_or(0, |run| run.start);
+        } else {
+            self.next = end + run.step;
+        }
+        (start, end, run.step)
+    }
+}
+
+impl FromIterator<u64> for SumsetApprox {
+    fn from_iter<I: IntoIterator<Item = u64>>(iter: I) -> Self {
+        let mut values = iter.into_iter().collect::<Vec<_>>();
+        values.sort_unstable();
+        SumsetApprox::from_sorted(values)
+    }
+}
+
+impl From<Vec<u64>> for SumsetApprox {
+    fn from(values: Vec<u64>) -> Self {
+        values.into_iter().collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::fft::{FFT, NTT};
+
+    use super::*;
+
+    fn verify_runs(values: Vec<u64>) {
+        let set = SumsetApprox::from(values.clone());
+        let mut expected = values;
+        expected.sort();
+        expected.dedup();
+        assert_eq!(set.to_vec(), expected);
+        assert_eq!(set.len(), expected.len());
+        for t in 0..=expected.last().copied().unwrap_or(0) + 2 {
+            assert_eq!(
+                set.max_at_most(t),
+                expected.iter().copied().filter(|&x| x <= t).max()
+            );
+            assert_eq!(
+                set.min_at_least(t),
+                expected.iter().copied().find(|&x| x >= t)
+            );
+            assert_eq!(set.contains(t), expected.contains(&t));
+        }
+    }
+
+    #[test]
+    fn test_runs() {
+        verify_runs(vec![]);
+        verify_runs(vec![7]);
+        verify_runs(vec![3, 1, 2, 3, 4, 10, 20, 30, 31, 33, 35, 37, 100]);
+        verify_runs((0..200).map(|x| x * 5).chain(1000..1010).collect());
+        assert_eq!(
+            SumsetApprox::from((0..100).map(|x| x * 7).collect::<Vec<_>>()).runs(),
+            &[Run {
+                start: 0,
+                end: 693,
+                step: 7
+            }]
+        );
+    }
+
+    #[test]
+    fn test_union() {
+        let a = SumsetApprox::from(vec![0, 2, 4, 6, 8, 11]);
+        let b = SumsetApprox::from(vec![1, 4, 7, 11, 12]);
+        assert_eq!(a.union(&b).to_vec(), vec![0, 1, 2, 4, 6, 7, 8, 11, 12]);
+        assert_eq!(a.union(&SumsetApprox::default()), a);
+        for (a, b) in [
+            (
+                (0..100).map(|x| x * 4).collect(),
+                (0..50).map(|x| x * 6).collect(),
+            ),
+            (
+                (0..20).map(|x| x * 3).collect(),
+                vec![1, 2, 30, 31, 57, 58, 59, 60],
+            ),
+            (vec![5, 10, 15], vec![15, 20, 25, 27]),
+        ] {
+            let expected = [a.clone(), b.clone()].concat().into_iter().collect();
+            let (a, b) = (SumsetApprox::from(a), SumsetApprox::from(b));
+            assert_eq!(a.union(&b), expected);
+            assert_eq!(b.union(&a), expected);
+        }
+        // Runs that do not interleave are copied whole, and continuing runs are joined.
+        let low = SumsetApprox::from((0..1000).map(|x| x * 5).collect::<Vec<_>>());
+        let high = SumsetApprox::from((1000..3000).map(|x| x * 5).collect::<Vec<_>>());
+        assert_eq!(low.union(&high).runs().len(), 1);
+        assert_eq!(low.union(&high).len(), 3000);
+        assert_eq!(
+            low.union(&high).clamped(12, 4998).to_vec(),
+            (3..1000).map(|x| x * 5).collect::<Vec<_>>()
+        );
+        assert!(low.clamped(6, 9).is_empty());
+    }
+
+    fn verify_merge<T: Convoluter>(a: Vec<u64>, b: Vec<u64>) {
+        let merged = SumsetApprox::from(a.clone()).merge::<T>(&SumsetApprox::from(b.clone()));
+        let expected = a
+            .iter()
+            .flat_map(|x| b.iter().map(move |y| x + y))
+            .collect::<SumsetApprox>();
+        assert_eq!(merged, expected);
+    }
+
+    #[test]
+    fn test_merge_fft() {
+        verify_merge::<FFT>(vec![0, 3, 6, 9], vec![0, 10, 20]);
+        verify_merge::<FFT>((0..100).collect(), (0..50).map(|x| x * 3).collect());
+        verify_merge::<FFT>(vec![], vec![1]);
+        verify_merge::<FFT>(vec![4], vec![9]);
+        verify_merge::<FFT>((0..50).map(|x| 5 + x * 1000).collect(), vec![7, 3007, 9007]);
+    }
+
+    #[test]
+    fn test_merge_ntt() {
+        verify_merge::<NTT>(vec![0, 3, 6, 9], vec![0, 10, 20]);
+        verify_merge::<NTT>((0..100).collect(), (0..50).map(|x| x * 3).collect());
+        verify_merge::<NTT>(vec![], vec![1]);
+        verify_merge::<NTT>(vec![4], vec![9]);
+        verify_merge::<NTT>((0..50).map(|x| 5 + x * 1000).collect(), vec![7, 3007, 9007]);
+    }
+}
@@ -1,9 +1,16 @@
+use std::{sync::Mutex, time::Duration};
+
 use crate::{
+    context::{CancellationToken, Context, Interrupted, Progress},
     fft::{Convoluter, FFT, NTT},
-    helpers::{naive_sumset, test::verify_approximation},
+    helpers::{count_multiplicities, naive_sumset, test::verify_approximation},
+    subset_sum::{bounded_copies_sumset, multiset_sumset},
 };
 
-use super::approximate_sumset;
+use super::{
+    approximate_bounded_copies_sumset, approximate_multiset_sumset, approximate_sumset,
+    approximate_sumset_with_context, merge_approximations, merge_approximations_with_context,
+};
 
 fn verify_unrestricted_approximation<T: Convoluter>(input: Vec<u16>, epsilon: f64) {
     let approximation = approximate_sumset::<T>(&input, epsilon);
@@ -55,6 +62,85 @@ fn test_unrestricted_approximation_u32_max_ntt() {
     verify_unrestricted_approximation::<NTT>(input, epsilon)
 }
 
+fn verify_repeated_approximation<T: Convoluter>(input: Vec<u16>, epsilon: f64) {
+    let input = input.into_iter().map(u64::from).collect::<Vec<_>>();
+    let classes = count_multiplicities(&input).into_iter().collect::<Vec<_>>();
+    let approximation = approximate_multiset_sumset::<T>(&classes, epsilon).to_vec();
+    let additive_error = (epsilon * input.iter().sum::<u64>() as f64) as u64 / 4;
+    verify_approximation(
+        &approximation,
+        &multiset_sumset::<T>(&input),
+        0.0,
+        additive_error,
+    );
+}
+
+#[test]
+fn test_repeated_approximation_fft() {
+    let input = [vec![100; 30], vec![77; 25], vec![3; 100], vec![4000; 2]].concat();
+    verify_repeated_approximation::<FFT>(input.clone(), 0.01);
+    verify_repeated_approximation::<FFT>(input, 0.2);
+    // Every multiplicity in binary, from one digit to many.
+    for mult in [1, 2, 3, 7, 8, 100, 1023, 5000] {
+        verify_repeated_approximation::<FFT>([vec![7; mult], vec![1000; 3]].concat(), 0.1);
+    }
+}
+
+fn verify_bounded_copies_approximation<T: Convoluter>(classes: &[(u64, usize)], t: u64) {
+    let epsilon = 0.05;
+    let approximation = approximate_bounded_copies_sumset::<T>(classes, t, epsilon).to_vec();
+    let additive_error = (epsilon * t as f64) as u64 / 4;
+    // Copies beyond `t` do not change the sums up to it.
+    let kept = classes
+        .iter()
+        .filter(|&&(value, _)| value != 0)
+        .map(|&(value, copies)| (value, copies.min((t / value) as usize)))
+        .collect::<Vec<_>>();
+    let exact = bounded_copies_sumset::<T>(&kept);
+    assert!(approximation.iter().all(|&x| x <= t));
+    verify_approximation(
+        &approximation,
+        &exact
+            .iter()
+            .copied()
+            .filter(|&x| x <= t)
+            .collect::<Vec<_>>(),
+        0.0,
+        additive_error,
+    );
+    for &a in &approximation {
+        assert!(exact.iter().any(|&s| a <= s && s <= a + additive_error));
+    }
+}
+
+#[test]
+fn test_bounded_copies_approximation() {
+    verify_bounded_copies_approximation::<FFT>(&[(97, 1000), (1000, 3), (1313, 40)], 20000);
+    verify_bounded_copies_approximation::<NTT>(&[(97, 1000), (1000, 3), (1313, 40)], 20000);
+    verify_bounded_copies_approximation::<FFT>(&[(50000, 2), (0, 5), (7, 3)], 1000);
+    verify_bounded_copies_approximation::<FFT>(&[], 1000);
+    // Every class reaches far beyond `t`, while the few sums below it are sparse.
+    let classes = (0..20).map(|i| (6007 + i * 701, 1000)).collect::<Vec<_>>();
+    verify_bounded_copies_approximation::<FFT>(&classes, 20000);
+    verify_bounded_copies_approximation::<NTT>(&classes, 20000);
+}
+
+#[test]
+fn test_repeated_approximation_ntt() {
+    let input = [vec![100; 30], vec![77; 25], vec![3; 100], vec![4000; 2]].concat();
+    verify_repeated_approximation::<NTT>(input.clone(), 0.01);
+    verify_repeated_approximation::<NTT>(input, 0.2);
+}
+
+#[test]
+fn test_unrestricted_approximation_repeated() {
+    let input = [vec![1000; 300], vec![777; 250], vec![3; 1000], vec![0; 20]].concat();
+    verify_unrestricted_approximation::<FFT>([vec![1000; 8], vec![3; 6]].concat(), 0.05);
+    let approximation = approximate_sumset::<FFT>(&input, 0.01);
+    assert_eq!(approximation.first(), Some(&0));
+    assert!(approximation.windows(2).all(|w| w[0] < w[1]));
+}
+
 #[test]
 fn test_unrestricted_approximation_large() {
     let input = [
@@ -66,6 +152,41 @@ fn test_unrestricted_approximation_large() {
     assert!(approximation.len() >= input.len())
 }
 
+#[test]
+fn test_interrupted_sumset() {
+    let input = (0..300u64)
+        .map(|x| (1000 + x * x % 997) as u16)
+        .collect::<Vec<_>>();
+    let cancellation = CancellationToken::new();
+    cancellation.cancel();
+    let context = Context::new().with_cancellation(cancellation);
+    assert_eq!(
+        approximate_sumset_with_context::<FFT>(&input, 0.1, &context),
+        Err(Interrupted::Cancelled)
+    );
+    let context = Context::new().with_timeout(Duration::ZERO);
+    assert_eq!(
+        merge_approximations_with_context::<NTT>(&[vec![0, 1], vec![0, 2]], &context),
+        Err(Interrupted::DeadlineExceeded)
+    );
+}
+
+#[test]
+fn test_progress() {
+    let levels = Mutex::new(vec![]);
+    let progress = |progress: Progress| levels.lock().unwrap().push(progress);
+    let context = Context::new().with_progress(&progress);
+    let a_js = (1..=4).map(|x| vec![0, x]).collect::<Vec<_>>();
+    let merged = merge_approximations_with_context::<FFT>(&a_js, &context).unwrap();
+    assert_eq!(merged, merge_approximations::<FFT>(&a_js));
+    let levels = levels.into_inner().unwrap();
+    assert_eq!(
+        levels.iter().map(|p| p.level).collect::<Vec<_>>(),
+        [1, 1, 2]
+    );
+    assert_eq!(levels.last().unwrap().merges, 3);
+}
+
 #[cfg(feature = "use-proptest")]
 mod proptest_tests {
     use super::*;
@@ -0,0 +1,74 @@
+use crate::{fft::Convoluter, subset_sum::unbounded_sumset};
+
+use super::{merge_zero_sumsets, Epsilon, SumsetApprox};
+
+/// Approximates the sums up to `t` that use each value any number of times.
+///
+/// Like [`unbounded_sumset`], the sums of at most `2^k` items are merged with themselves, here
+/// with a multiplicative error of `epsilon / k` per round over the `k` rounds needed. Every sum up
+/// to `t` has an approximation in `[(1 - epsilon) * s, s]`, and every approximation `a` has a sum
+/// in `[a, a / (1 - epsilon)]`, which may itself lie slightly above `t`.
+pub fn approximate_unbounded_sumset<T: Convoluter>(
+    values: &[u64],
+    t: u64,
+    epsilon: impl Into<Epsilon>,
+) -> SumsetApprox {
+    let values = values
+        .iter()
+        .copied()
+        .filter(|&x| x != 0 && x <= t)
+        .collect::<Vec<_>>();
+    let Some(&min) = values.iter().min() else {
+        return SumsetApprox::from_sorted([0]);
+    };
+    let rounds = (t / min).next_power_of_two().trailing_zeros();
+    if rounds == 0 {
+        return unbounded_sumset::<T>(&values, t).into();
+    }
+    let delta = epsilon.into().divide(rounds as u64);
+    let mut sums = SumsetApprox::from_iter(values.into_iter().chain([0])).to_vec();
+    for _ in 0..rounds {
+        sums = merge_zero_sumsets::<T>(&sums, &sums, t, delta);
+    }
+    sums.into()
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        fft::{FFT, NTT},
+        helpers::test::verify_element_in_approximation,
+    };
+
+    use super::*;
+
+    fn verify_unbounded<T: Convoluter>(values: &[u64], t: u64, epsilon: f64) {
+        let approximation = approximate_unbounded_sumset::<T>(values, t, epsilon).to_vec();
+        let reachable = unbounded_sumset::<T>(values, 2 * t);
+        for &s in reachable.iter().filter(|&&s| s <= t) {
+            verify_element_in_approximation(&approximation, s, epsilon, 0);
+        }
+        for &a in approximation.iter() {
+            assert!(a <= t);
+            assert!(
+                reachable
+                    .iter()
+                    .any(|&s| s >= a && ((1.0 - epsilon) * s as f64) as u64 <= a),
+                "{a} has no reachable sum"
+            );
+        }
+    }
+
+    #[test]
+    fn test_unbounded_fft() {
+        verify_unbounded::<FFT>(&[97, 1000, 1313], 20000, 0.1);
+        verify_unbounded::<FFT>(&[5000], 20000, 0.1);
+        verify_unbounded::<FFT>(&[], 100, 0.1);
+    }
+
+    #[test]
+    fn test_unbounded_ntt() {
+        verify_unbounded::<NTT>(&[97, 1000, 1313], 20000, 0.1);
+        verify_unbounded::<NTT>(&[30000], 20000, 0.1);
+    }
+}
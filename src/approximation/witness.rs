@@ -0,0 +1,458 @@
+use crate::{context::Context, fft::Convoluter, subset_sum::subset_sum};
+
+use super::{Epsilon, MultiplicativeBoundedMerger, SumsetApprox};
+
+/// Which sums count as close to a target.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Side {
+    /// The sum may not exceed the target.
+    #[default]
+    Below,
+    /// The sum may lie on either side of the target.
+    Nearest,
+}
+
+/// One option picked from every group, together with the total.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Choice {
+    pub sum: u64,
+    pub picks: Vec<usize>,
+}
+
+/// Picks one option from every group so that the total is close to `target`.
+///
+/// The groups are merged pairwise in a balanced tree with the [`MultiplicativeBoundedMerger`],
+/// and every node keeps its approximate totals, so a witness is read back by walking down the
+/// tree. Every merge rounds a pair of totals down by a factor of at most `1 - delta` with
+/// `delta = epsilon / (4 * levels)`, so a total `a` of a node with `h` rounding merges below it
+/// stands for an actual total between `a` and `a * D / (D - h)`, where `D = 1 / delta`.
+///
+/// With [`Side::Nearest`] a total of a node is split into a pair of totals of its children that
+/// sums to it or slightly more, the root totals just below and above `target` are recovered and
+/// the closer choice is returned; its distance to `target` is at most `epsilon * target` worse
+/// than the best. With [`Side::Below`] the result does not exceed `target` and is at most
+/// `epsilon * target` below the best such total. The root totals not exceeding `target` are
+/// recovered from the largest down, next to a choice whose every node splits a budget between
+/// its children by the bounds above, which always fits. The best total is at most the bound of
+/// the largest root total, so a fitting choice within `epsilon * target` of that bound is
+/// certified. When totals cluster just below and above `target` none may be, and the tree is
+/// built again with half of the delta; at worst the merges become exact and the largest total
+/// is the best one. Returns `None` when no choice fits. Without groups the only choice is the
+/// empty one, summing to 0.
+///
+/// Each merge takes `O(log(target) * levels / epsilon)` time up to logarithmic factors, and
+/// recovering a choice takes time linear in the totals kept in the tree.
+pub fn approximate_choice<T: Convoluter>(
+    groups: &[Vec<u64>],
+    target: u64,
+    epsilon: impl Into<Epsilon>,
+    side: Side,
+) -> Option<Choice> {
+    if groups.iter().any(|group| group.is_empty()) {
+        return None;
+    }
+    if groups.is_empty() {
+        return Some(Choice {
+            sum: 0,
+            picks: vec![],
+        });
+    }
+    let smallest = Choice {
+        sum: groups.iter().map(|group| group.iter().min().unwrap()).sum(),
+        picks: groups
+            .iter()
+            .map(|group| (0..group.len()).min_by_key(|&i| group[i]).unwrap())
+            .collect(),
+    };
+    // Nothing gets closer than the smallest total once it reaches the target.
+    if smallest.sum >= target {
+        return (side == Side::Nearest || smallest.sum == target).then_some(smallest);
+    }
+    let epsilon = epsilon.into();
+    let levels = groups.len().next_power_of_two().trailing_zeros() + 1;
+    let mut delta = epsilon.divide(4 * levels as u64);
+    let choice = |picks: Vec<usize>| Choice {
+        sum: picks
+            .iter()
+            .zip(groups)
+            .map(|(&pick, group)| group[pick])
+            .sum(),
+        picks,
+    };
+    if side == Side::Nearest {
+        // The best total is below `target`, or above it by less than `target`.
+        let tree = MergeNode::build::<T>(groups, target.saturating_mul(2), delta);
+        return [
+            tree.sums.max_at_most(target),
+            tree.sums.min_at_least(target),
+        ]
+        .into_iter()
+        .flatten()
+        .map(|sum| choice(tree.picks(groups, sum)))
+        .min_by_key(|choice| choice.sum.abs_diff(target));
+    }
+    let error = epsilon.floor_mul(target, 1);
+    loop {
+        let tree = MergeNode::build::<T>(groups, target, delta);
+        let d = delta.inverse();
+        let largest = tree.sums.max_at_most(target)?;
+        let certified = tree.upper(largest, d).min(target).saturating_sub(error);
+        let mut picks = Vec::with_capacity(groups.len());
+        tree.collect_picks_below(groups, target, d, &mut picks)?;
+        let budgeted = choice(picks);
+        let below = |x: u64| x.checked_sub(1).and_then(|x| tree.sums.max_at_most(x));
+        let found = std::iter::successors(Some(largest), |&x| below(x))
+            .take_while(|&x| x > budgeted.sum && x >= certified)
+            .map(|sum| choice(tree.picks(groups, sum)))
+            .find(|choice| choice.sum <= target)
+            .filter(|choice| choice.sum > budgeted.sum)
+            .unwrap_or(budgeted);
+        if found.sum >= certified {
+            return Some(found);
+        }
+        delta = delta.divide(2);
+    }
+}
+
+struct MergeNode {
+    sums: SumsetApprox,
+    // The number of rounding merges on the longest path down to a group.
+    height: u64,
+    children: Option<Box<(MergeNode, MergeNode)>>,
+}
+
+impl MergeNode {
+    fn build<T: Convoluter>(groups: &[Vec<u64>], cap: u64, delta: Epsilon) -> Self {
+        if let [group] = groups {
+            return MergeNode {
+                sums: group.iter().copied().filter(|&x| x <= cap).collect(),
+                height: 0,
+                children: None,
+            };
+        }
+        let (left, right) = groups.split_at(groups.len() / 2);
+        let (left, right) = (
+            Self::build::<T>(left, cap, delta),
+            Self::build::<T>(right, cap, delta),
+        );
+        // With few pairs, or a cap below the `24 / delta` size of the merger's widest interval,
+        // one exact convolution is cheaper than the merger.
+        let exact = left.sums.len() * right.sums.len() < 1000 || cap <= 12 * delta.inverse();
+        let mut sums = if exact {
+            subset_sum::<T>(&left.sums.to_vec(), &right.sums.to_vec())
+                .into_iter()
+                .take_while(|&x| x <= cap)
+                .collect()
+        } else {
+            MultiplicativeBoundedMerger::<T>::new(1, 1, delta, cap.max(1))
+                .merge_runs_with_context(&left.sums, &right.sums, &Context::new())
+                .expect("the context never interrupts")
+        };
+        // The merger starts at 1, so a total of 0 has to be added back.
+        if left.sums.contains(0) && right.sums.contains(0) {
+            sums = sums.union(&SumsetApprox::from_sorted([0]));
+        }
+        MergeNode {
+            sums,
+            height: left.height.max(right.height) + u64::from(!exact),
+            children: Some(Box::new((left, right))),
+        }
+    }
+    // The option picked from every group for one of the totals.
+    fn picks(&self, groups: &[Vec<u64>], sum: u64) -> Vec<usize> {
+        let mut picks = Vec::with_capacity(groups.len());
+        self.collect_picks(groups, sum, &mut picks);
+        picks
+    }
+    // The largest actual total an approximate total `a` of this node can stand for,
+    // `floor(a * D / (D - height))` with `D = 1 / delta`.
+    fn upper(&self, a: u64, d: u64) -> u64 {
+        (a as u128 * d as u128 / (d - self.height) as u128) as u64
+    }
+    // The largest approximate total whose upper bound is at most `budget`.
+    fn largest_within(&self, budget: u64, d: u64) -> Option<u64> {
+        // `a * D / (D - h) < budget + 1` exactly when `a * D < (budget + 1) * (D - h)`.
+        let limit = ((budget as u128 + 1) * (d - self.height) as u128).div_ceil(d as u128) - 1;
+        self.sums.max_at_most(limit as u64)
+    }
+    // Appends the option picked from every group of this node for an actual total of at most
+    // `budget` and at least the largest approximate total `a` with `upper(a) <= budget`.
+    //
+    // Such an `a` comes from a pair `(x, y)` of totals of the children with `x + y = a`, or
+    // `x + y <= a / (1 - delta)` when the merge rounds, and then
+    // `upper(x) + upper(y) <= upper(a) <= budget`. So the pair maximizing `x + y` among those
+    // fitting the budget sums to at least `a`, and each child recovers at least its part within
+    // its own bound.
+    fn collect_picks_below(
+        &self,
+        groups: &[Vec<u64>],
+        budget: u64,
+        d: u64,
+        picks: &mut Vec<usize>,
+    ) -> Option<()> {
+        let Some(children) = &self.children else {
+            let best = self.sums.max_at_most(budget)?;
+            picks.push(groups[0].iter().position(|&x| x == best).unwrap());
+            return Some(());
+        };
+        let (left, right) = &**children;
+        let (_, a, b) = left
+            .sums
+            .iter()
+            .map_while(|a| budget.checked_sub(left.upper(a, d)).map(|rest| (a, rest)))
+            .filter_map(|(a, rest)| Some((a, right.largest_within(rest, d)?)))
+            .map(|(a, b)| (a + b, a, b))
+            .max()?;
+        let (left_groups, right_groups) = groups.split_at(groups.len() / 2);
+        left.collect_picks_below(left_groups, left.upper(a, d), d, picks)?;
+        right.collect_picks_below(right_groups, right.upper(b, d), d, picks)
+    }
+    // Appends the option picked from every group of this node for one of its totals.
+    fn collect_picks(&self, groups: &[Vec<u64>], sum: u64, picks: &mut Vec<usize>) {
+        let Some(children) = &self.children else {
+            picks.push(groups[0].iter().position(|&x| x == sum).unwrap());
+            return;
+        };
+        let (left, right) = &**children;
+        // The merger only rounds down, so some pair sums to `sum` or a little more.
+        let (_, a, b) = left
+            .sums
+            .iter()
+            .filter_map(|a| {
+                let b = right.sums.min_at_least(sum.saturating_sub(a))?;
+                Some((a + b, a, b))
+            })
+            .min()
+            .expect("every total comes from a pair of totals of the children");
+        let (left_groups, right_groups) = groups.split_at(groups.len() / 2);
+        left.collect_picks(left_groups, a, picks);
+        right.collect_picks(right_groups, b, picks);
+    }
+}
+
+/// Picks a subset of `input` whose sum is close to `target`, see [`approximate_choice`] for the
+/// guarantee. Returns the indices of the picked items.
+pub fn approximate_subset<T: Convoluter>(
+    input: &[u64],
+    target: u64,
+    epsilon: impl Into<Epsilon>,
+    side: Side,
+) -> Choice {
+    let groups = input.iter().map(|&x| vec![0, x]).collect::<Vec<_>>();
+    let choice = approximate_choice::<T>(&groups, target, epsilon, side)
+        .expect("the empty subset always fits");
+    Choice {
+        sum: choice.sum,
+        picks: (0..input.len()).filter(|&i| choice.picks[i] == 1).collect(),
+    }
+}
+
+/// Picks exactly one option from every group so that the total is as close as possible to the
+/// `target` budget without exceeding it.
+///
+/// This is the witness-carrying counterpart of merging the groups with
+/// [`merge_approximations`](super::merge_approximations): the groups are merged with the same
+/// bounded mergers by [`approximate_choice`], which keeps the merged sums of every level to read
+/// the choice back. The total is at most `epsilon * target` below the best total within the
+/// budget, and `picks[i]` indexes into `groups[i]`. Returns `None` when even the cheapest options
+/// exceed the budget.
+pub fn multiple_choice_subset_sum<T: Convoluter>(
+    groups: &[Vec<u64>],
+    target: u64,
+    epsilon: impl Into<Epsilon>,
+) -> Option<Choice> {
+    approximate_choice::<T>(groups, target, epsilon, Side::Below)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::fft::{FFT, NTT};
+
+    use super::*;
+
+    fn all_choices(groups: &[Vec<u64>]) -> Vec<u64> {
+        groups.iter().fold(vec![0], |sums, group| {
+            sums.iter()
+                .flat_map(|s| group.iter().map(move |x| s + x))
+                .collect()
+        })
+    }
+
+    fn verify_choice<T: Convoluter>(groups: &[Vec<u64>], target: u64, epsilon: f64, side: Side) {
+        let totals = all_choices(groups);
+        let error = epsilon * target as f64;
+        let choice = approximate_choice::<T>(groups, target, epsilon, side);
+        match side {
+            Side::Below => {
+                let best = totals.iter().copied().filter(|&x| x <= target).max();
+                let Some(best) = best else {
+                    assert_eq!(choice, None);
+                    return;
+                };
+                let choice = choice.unwrap();
+                assert!(choice.sum <= target);
+                assert!((best - choice.sum) as f64 <= error, "{best} {choice:?}");
+            }
+            Side::Nearest => {
+                let best = totals.iter().map(|x| x.abs_diff(target)).min().unwrap();
+                let choice = choice.unwrap();
+                assert!((choice.sum.abs_diff(target) - best) as f64 <= error);
+            }
+        }
+        let choice = approximate_choice::<T>(groups, target, epsilon, side).unwrap();
+        assert_eq!(choice.picks.len(), groups.len());
+        let picked: u64 = choice
+            .picks
+            .iter()
+            .zip(groups)
+            .map(|(&pick, group)| group[pick])
+            .sum();
+        assert_eq!(picked, choice.sum);
+    }
+
+    fn verify_choices<T: Convoluter>() {
+        let groups = vec![
+            vec![3, 10, 40],
+            vec![7, 8],
+            vec![100, 1, 55],
+            vec![20, 30, 25, 22],
+        ];
+        for target in [0, 20, 31, 60, 99, 150, 180, 1000] {
+            for epsilon in [0.5, 0.1, 0.01] {
+                verify_choice::<T>(&groups, target, epsilon, Side::Below);
+                verify_choice::<T>(&groups, target, epsilon, Side::Nearest);
+            }
+        }
+    }
+
+    #[test]
+    fn test_choice_fft() {
+        verify_choices::<FFT>();
+    }
+
+    #[test]
+    fn test_choice_ntt() {
+        verify_choices::<NTT>();
+    }
+
+    #[test]
+    fn test_multiple_choice_subset_sum() {
+        let groups = vec![vec![500, 120, 800], vec![60, 300], vec![1000, 900, 10]];
+        let choice = multiple_choice_subset_sum::<FFT>(&groups, 1000, 0.001).unwrap();
+        assert_eq!((choice.sum, choice.picks), (870, vec![2, 0, 2]));
+        assert_eq!(multiple_choice_subset_sum::<FFT>(&groups, 189, 0.1), None);
+        let empty = multiple_choice_subset_sum::<FFT>(&[], 10, 0.1).unwrap();
+        assert_eq!((empty.sum, empty.picks), (0, vec![]));
+        // Large enough options that the bounded mergers round.
+        let groups = (0..10)
+            .map(|i| {
+                (0..4)
+                    .map(|j| (i * 7919 + j * 104729) % 50000 + 1)
+                    .collect()
+            })
+            .collect::<Vec<Vec<u64>>>();
+        for target in [100000, 170000, 250000, 400000] {
+            verify_choice::<NTT>(&groups, target, 0.05, Side::Below);
+        }
+    }
+
+    // The largest total of at most `target`, by a table over the sums up to it.
+    fn best_below(groups: &[Vec<u64>], target: u64) -> Option<u64> {
+        let mut reachable = vec![false; target as usize + 1];
+        reachable[0] = true;
+        for group in groups {
+            let mut next = vec![false; reachable.len()];
+            for sum in (0..reachable.len()).filter(|&sum| reachable[sum]) {
+                for &x in group {
+                    if let Some(slot) = next.get_mut(sum + x as usize) {
+                        *slot = true;
+                    }
+                }
+            }
+            reachable = next;
+        }
+        reachable.iter().rposition(|&r| r).map(|sum| sum as u64)
+    }
+
+    #[test]
+    fn test_many_groups() {
+        // Many groups of large options make the merger round at every level, and put many
+        // totals just below and just above the target.
+        let groups = (0..100)
+            .map(|i| {
+                (0..3)
+                    .map(|j| (i * 7919 + j * 104729) % 20000 + 5000)
+                    .collect()
+            })
+            .collect::<Vec<Vec<u64>>>();
+        for target in [1_000_000, 1_250_003, 1_500_000] {
+            for epsilon in [0.5, 0.05, 0.01] {
+                let best = best_below(&groups, target).unwrap();
+                let choice = multiple_choice_subset_sum::<FFT>(&groups, target, epsilon).unwrap();
+                assert!(choice.sum <= target);
+                assert!((best - choice.sum) as f64 <= epsilon * target as f64);
+                let picked: u64 = choice
+                    .picks
+                    .iter()
+                    .zip(&groups)
+                    .map(|(&pick, group)| group[pick])
+                    .sum();
+                assert_eq!(picked, choice.sum);
+            }
+        }
+    }
+
+    #[test]
+    fn test_clustered_totals() {
+        // Every subset of k items sums to about k times the same value, so the totals cluster
+        // just below and above the target, where a rounded total easily recovers too much.
+        let cases: [(&[u64], u64, f64); 2] = [
+            (
+                &[
+                    92430, 90092, 91562, 90574, 89571, 90501, 91839, 91556, 90385, 91614, 89970,
+                    90211, 92105, 91872, 90943, 90623, 89575, 91808,
+                ],
+                358364,
+                0.2,
+            ),
+            (
+                &[
+                    80001, 80043, 80051, 80291, 80622, 80684, 79958, 80818, 80207, 80741, 80908,
+                    80335, 81049, 80883, 79914, 80595, 80825, 80765, 81031, 80557, 80236, 80385,
+                    80263, 79936, 80122, 80934, 80988, 80189, 80474, 80667, 80983, 80924, 80395,
+                    80394, 80954, 80663, 80070, 80209, 80597, 79968, 80071, 80702, 81027, 79940,
+                    80908, 80421,
+                ],
+                1200096,
+                0.05,
+            ),
+        ];
+        for (input, target, epsilon) in cases {
+            let groups = input.iter().map(|&x| vec![0, x]).collect::<Vec<_>>();
+            let best = best_below(&groups, target).unwrap();
+            let choice = multiple_choice_subset_sum::<FFT>(&groups, target, epsilon).unwrap();
+            assert!(choice.sum <= target);
+            assert!((best - choice.sum) as f64 <= epsilon * target as f64);
+        }
+    }
+
+    #[test]
+    fn test_subset() {
+        let input = [
+            1001, 2000, 3000, 4000, 5000, 6000, 7000, 1000, 1001, 1002, 1003, 5,
+        ];
+        let sigma: u64 = input.iter().sum();
+        let groups = input.iter().map(|&x| vec![0, x]).collect::<Vec<_>>();
+        for target in [0, 1, sigma / 3, sigma / 2, sigma * 7 / 10, sigma] {
+            for epsilon in [0.1, 0.01, 0.001] {
+                verify_choice::<FFT>(&groups, target, epsilon, Side::Below);
+                verify_choice::<FFT>(&groups, target, epsilon, Side::Nearest);
+                let subset = approximate_subset::<FFT>(&input, target, epsilon, Side::Below);
+                assert_eq!(
+                    subset.picks.iter().map(|&i| input[i]).sum::<u64>(),
+                    subset.sum
+                );
+            }
+        }
+    }
+}
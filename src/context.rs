@@ -0,0 +1,143 @@
+use std::{
+    fmt::{self, Display},
+    sync::{
+        atomic::{AtomicBool, AtomicU64, Ordering},
+        Arc,
+    },
+    time::{Duration, Instant},
+};
+
+/// A flag shared between a long-running computation and whoever may stop it. Clones share the
+/// flag.
+#[derive(Debug, Clone, Default)]
+pub struct CancellationToken(Arc<AtomicBool>);
+
+impl CancellationToken {
+    pub fn new() -> Self {
+        Self::default()
+    }
+    pub fn cancel(&self) {
+        self.0.store(true, Ordering::Relaxed);
+    }
+    pub fn is_cancelled(&self) -> bool {
+        self.0.load(Ordering::Relaxed)
+    }
+}
+
+/// Why a computation stopped early.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Interrupted {
+    Cancelled,
+    DeadlineExceeded,
+}
+
+impl Display for Interrupted {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Interrupted::Cancelled => write!(f, "the computation was cancelled"),
+            Interrupted::DeadlineExceeded => write!(f, "the computation ran past its deadline"),
+        }
+    }
+}
+
+impl std::error::Error for Interrupted {}
+
+/// Reported to the progress callback after every merge.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Progress {
+    /// The height of the merged node in its merge tree, 1 for merging two leaves.
+    pub level: u32,
+    /// The number of merges done so far with this context.
+    pub merges: u64,
+}
+
+/// Controls a long-running computation: it is checked between merges, stops the computation
+/// with [`Interrupted`] once cancelled or past its deadline, and reports [`Progress`].
+#[derive(Default)]
+pub struct Context<'a> {
+    cancellation: CancellationToken,
+    deadline: Option<Instant>,
+    progress: Option<&'a (dyn Fn(Progress) + Sync)>,
+    merges: AtomicU64,
+}
+
+impl<'a> Context<'a> {
+    pub fn new() -> Self {
+        Self::default()
+    }
+    pub fn with_cancellation(mut self, cancellation: CancellationToken) -> Self {
+        self.cancellation = cancellation;
+        self
+    }
+    pub fn with_deadline(mut self, deadline: Instant) -> Self {
+        self.deadline = Some(deadline);
+        self
+    }
+    pub fn with_timeout(self, timeout: Duration) -> Self {
+        self.with_deadline(Instant::now() + timeout)
+    }
+    pub fn with_progress(mut self, progress: &'a (dyn Fn(Progress) + Sync)) -> Self {
+        self.progress = Some(progress);
+        self
+    }
+    pub fn cancellation(&self) -> &CancellationToken {
+        &self.cancellation
+    }
+    pub fn check(&self) -> Result<(), Interrupted> {
+        if self.cancellation.is_cancelled() {
+            return Err(Interrupted::Cancelled);
+        }
+        if self
+            .deadline
+            .is_some_and(|deadline| Instant::now() >= deadline)
+        {
+            return Err(Interrupted::DeadlineExceeded);
+        }
+        Ok(())
+    }
+    /// Counts a finished merge at `level` and reports it.
+    pub(crate) fn merged(&self, level: u32) {
+        let merges = self.merges.fetch_add(1, Ordering::Relaxed) + 1;
+        if let Some(progress) = self.progress {
+            progress(Progress { level, merges });
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Mutex;
+
+    use super::*;
+
+    #[test]
+    fn test_context() {
+        let cancellation = CancellationToken::new();
+        let context = Context::new().with_cancellation(cancellation.clone());
+        assert_eq!(context.check(), Ok(()));
+        cancellation.cancel();
+        assert_eq!(context.check(), Err(Interrupted::Cancelled));
+
+        let context = Context::new().with_timeout(Duration::ZERO);
+        assert_eq!(context.check(), Err(Interrupted::DeadlineExceeded));
+
+        let reports = Mutex::new(vec![]);
+        let progress = |progress| reports.lock().unwrap().push(progress);
+        let context = Context::new().with_progress(&progress);
+        context.merged(1);
+        context.merged(2);
+        assert_eq!(
+            *reports.lock().unwrap(),
+            [
+                Progress {
+                    level: 1,
+                    merges: 1
+                },
+                Progress {
+                    level: 2,
+                    merges: 2
+                }
+            ]
+        );
+    }
+}
@@ -0,0 +1,217 @@
+use crate::{context::Context, meet_in_the_middle::SubsetWitness, solver::SolverError};
+
+/// The largest target a table is built for. Its `2^35` bits take 4 GiB, and filling it would take
+/// far too long anyway, so larger targets fail up front instead of depending on the allocator.
+pub const MAX_TABLE_TARGET: u64 = 1 << 35;
+
+/// The reachable sums `0..=t`, one bit each, packed into words.
+struct Bitset {
+    words: Vec<u64>,
+    t: u64,
+}
+
+impl Bitset {
+    /// Only the empty sum. The size is checked against [`MAX_TABLE_TARGET`] and a failed
+    /// allocation is reported rather than aborting, since `t` comes straight from the input.
+    fn new(t: u64) -> Result<Self, SolverError> {
+        let too_large = SolverError::TableTooLarge { target: t };
+        if t > MAX_TABLE_TARGET {
+            return Err(too_large);
+        }
+        let len = usize::try_from(t / 64 + 1).map_err(|_| too_large.clone())?;
+        let mut words = Vec::new();
+        words.try_reserve_exact(len).map_err(|_| too_large)?;
+        words.resize(len, 0);
+        words[0] = 1;
+        Ok(Self { words, t })
+    }
+    fn contains(&self, sum: u64) -> bool {
+        sum <= self.t && self.words[(sum / 64) as usize] >> (sum % 64) & 1 == 1
+    }
+    /// Adds `x` to every sum while keeping the old ones, `self |= self << x`. The words are
+    /// updated from the top, so every word is read before it is written.
+    fn add_item(&mut self, x: u64) {
+        if x > self.t {
+            return;
+        }
+        let (shift, bits) = ((x / 64) as usize, x % 64);
+        for i in (shift..self.words.len()).rev() {
+            let mut shifted = self.words[i - shift] << bits;
+            if bits > 0 && i > shift {
+                shifted |= self.words[i - shift - 1] >> (64 - bits);
+            }
+            self.words[i] |= shifted;
+        }
+        let last = self.t % 64;
+        if last < 63 {
+            *self.words.last_mut().unwrap() &= (1 << (last + 1)) - 1;
+        }
+    }
+    fn max(&self) -> u64 {
+        let (i, word) = self
+            .words
+            .iter()
+            .enumerate()
+            .rev()
+            .find(|(_, &word)| word != 0)
+            .expect("the empty sum is always reachable");
+        i as u64 * 64 + 63 - word.leading_zeros() as u64
+    }
+}
+
+fn reachable(
+    items: impl IntoIterator<Item = u64>,
+    t: u64,
+    context: &Context,
+) -> Result<Bitset, SolverError> {
+    let mut sums = Bitset::new(t)?;
+    for x in items {
+        context.check()?;
+        sums.add_item(x);
+    }
+    Ok(sums)
+}
+
+/// The largest subset sum of `input` not exceeding `t`, exactly.
+///
+/// The reachable sums are kept as a bitset, so adding an item is a single shift-OR over
+/// `t / 64` words. This takes `O(n * t / 64)` time and `t / 8` bytes.
+pub fn bitset_subset_sum(input: &[u64], t: u64) -> Result<u64, SolverError> {
+    bitset_subset_sum_with_context(input, t, &Context::new())
+}
+
+/// Like [`bitset_subset_sum`], but checks `context` before every item and fails with
+/// [`SolverError::Interrupted`] once it is cancelled or past its deadline.
+pub fn bitset_subset_sum_with_context(
+    input: &[u64],
+    t: u64,
+    context: &Context,
+) -> Result<u64, SolverError> {
+    Ok(reachable(input.iter().copied(), t, context)?.max())
+}
+
+/// Like [`bitset_subset_sum`], but also finds the items.
+///
+/// Keeping every row of the table for backtracking would take `n * t / 8` bytes. Instead, like
+/// in Hirschberg's algorithm, the items are split in halves, the sum is divided between the two
+/// halves by comparing their tables, and each half is solved for its share recursively. Only two
+/// tables are alive at a time, and the shares on one level of the recursion add up to the sum,
+/// so this takes `O(n * t / 64 * log n)` time and still `O(t / 8)` bytes.
+pub fn bitset_subset_sum_witness(input: &[u64], t: u64) -> Result<SubsetWitness, SolverError> {
+    let sum = bitset_subset_sum(input, t)?;
+    let indices = (0..input.len())
+        .filter(|&i| input[i] <= sum)
+        .collect::<Vec<_>>();
+    let mut items = Vec::new();
+    recover(input, &indices, sum, &Context::new(), &mut items)?;
+    items.sort_unstable();
+    Ok(SubsetWitness {
+        items,
+        sum: sum as u128,
+    })
+}
+
+// Appends to `items` a subset of `indices` that sums to exactly `target`, which must be reachable.
+fn recover(
+    input: &[u64],
+    indices: &[usize],
+    target: u64,
+    context: &Context,
+    items: &mut Vec<usize>,
+) -> Result<(), SolverError> {
+    if target == 0 {
+        return Ok(());
+    }
+    if let [index] = indices {
+        debug_assert_eq!(input[*index], target);
+        items.push(*index);
+        return Ok(());
+    }
+    let (left, right) = indices.split_at(indices.len() / 2);
+    let share = {
+        let left_sums = reachable(left.iter().map(|&i| input[i]), target, context)?;
+        let right_sums = reachable(right.iter().map(|&i| input[i]), target, context)?;
+        (0..=target)
+            .find(|&a| left_sums.contains(a) && right_sums.contains(target - a))
+            .expect("the target is reachable")
+    };
+    recover(input, left, share, context, items)?;
+    recover(input, right, target - share, context, items)
+}
+
+/// [`bitset_subset_sum_witness`] for half of the total, which is checked for overflow first.
+pub fn bitset_partition(input: &[u64]) -> Result<SubsetWitness, SolverError> {
+    let sigma = input
+        .iter()
+        .try_fold(0u64, |sum, &x| sum.checked_add(x))
+        .ok_or(SolverError::SumOverflow)?;
+    bitset_subset_sum_witness(input, sigma / 2)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{context::Interrupted, helpers::naive_sumset, solver::SolverError};
+
+    use super::*;
+
+    fn verify_bitset(input: &[u64], t: u64) {
+        let expected = naive_sumset(input)
+            .into_iter()
+            .filter(|&x| x <= t)
+            .max()
+            .unwrap_or(0);
+        assert_eq!(bitset_subset_sum(input, t), Ok(expected));
+        let witness = bitset_subset_sum_witness(input, t).unwrap();
+        assert_eq!(witness.sum, expected as u128);
+        assert!(witness.items.windows(2).all(|pair| pair[0] < pair[1]));
+        assert_eq!(
+            witness.items.iter().map(|&i| input[i]).sum::<u64>(),
+            expected
+        );
+    }
+
+    #[test]
+    fn test_bitset_subset_sum() {
+        verify_bitset(&[], 10);
+        verify_bitset(&[0, 0, 5], 4);
+        verify_bitset(&[63, 64, 65, 130, 1, 7], 200);
+        verify_bitset(&[63, 64, 65, 130, 1, 7], 128);
+        verify_bitset(&[1000, 2000, 3001, 64, 127, 128, 129, 4999], 5000);
+        let input = (1..=18).map(|i| i * 7919 % 1013 + 1).collect::<Vec<_>>();
+        for t in [0, 1, 100, 1000, 3000, 5000] {
+            verify_bitset(&input, t);
+        }
+    }
+
+    #[test]
+    fn test_bitset_partition() {
+        let input = [3, 5, 8, 13, 21, 34, 55];
+        let witness = bitset_partition(&input).unwrap();
+        assert_eq!(witness.sum, 68);
+        assert_eq!(
+            bitset_partition(&[u64::MAX, 1]),
+            Err(SolverError::SumOverflow)
+        );
+        assert_eq!(
+            bitset_subset_sum(&[1], u64::MAX),
+            Err(SolverError::TableTooLarge { target: u64::MAX })
+        );
+        assert_eq!(
+            bitset_subset_sum(&[1], MAX_TABLE_TARGET + 1),
+            Err(SolverError::TableTooLarge {
+                target: MAX_TABLE_TARGET + 1
+            })
+        );
+    }
+
+    #[test]
+    fn test_bitset_subset_sum_with_context() {
+        let context = Context::new();
+        assert_eq!(bitset_subset_sum_with_context(&[3, 5], 7, &context), Ok(5));
+        context.cancellation().cancel();
+        assert_eq!(
+            bitset_subset_sum_with_context(&[3, 5], 7, &context),
+            Err(SolverError::Interrupted(Interrupted::Cancelled))
+        );
+    }
+}
@@ -0,0 +1,328 @@
+use std::collections::{hash_map::Entry, HashMap, HashSet};
+
+use crate::{fft::Convoluter, subset_sum::subset_sum_2d};
+
+/// Two disjoint, non-empty subsets with equal sums, given by item indices.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct EqualSubsets {
+    pub first: Vec<usize>,
+    pub second: Vec<usize>,
+    pub sum: u64,
+}
+
+impl EqualSubsets {
+    fn new(input: &[u64], mut first: Vec<usize>, mut second: Vec<usize>) -> Self {
+        first.sort_unstable();
+        second.sort_unstable();
+        let sum = first.iter().map(|&i| input[i]).sum();
+        debug_assert_eq!(sum, second.iter().map(|&i| input[i]).sum::<u64>());
+        EqualSubsets { first, second, sum }
+    }
+}
+
+/// Inputs up to this many items are searched by meet-in-the-middle.
+const MEET_IN_THE_MIDDLE_ITEMS: usize = 24;
+
+/// Checks whether two disjoint, non-empty subsets of `input` have equal sums, and returns them.
+///
+/// Zeros and repeated values are answered directly. When the `k` smallest items have more subsets
+/// than possible sums, a pair is guaranteed among them and only those are searched, see
+/// [`pigeonhole_equal_subsets`], whose dynamic program answers more than 24 such items. Up to 24
+/// items are otherwise searched by meet-in-the-middle over the `3^(n/2)` differences of each
+/// half, larger inputs by a convolution tree over `(sum, used items)` pairs in which every item
+/// is shifted to `{-w, 0, w} + w`.
+pub fn equal_subset_sum<T: Convoluter>(input: &[u64]) -> Option<EqualSubsets> {
+    if let Some(trivial) = trivial_equal_subsets(input) {
+        return Some(trivial);
+    }
+    let mut items = (0..input.len())
+        .filter(|&i| input[i] != 0)
+        .collect::<Vec<_>>();
+    items.sort_by_key(|&i| input[i]);
+    if let Some(k) = pigeonhole_prefix(input, &items) {
+        items.truncate(k);
+        if k > MEET_IN_THE_MIDDLE_ITEMS {
+            if let Some(subsets) = first_collision(input, &items) {
+                return Some(subsets);
+            }
+        }
+    }
+    if items.len() <= MEET_IN_THE_MIDDLE_ITEMS {
+        meet_in_the_middle(input, &items)
+    } else {
+        convolution_tree::<T>(input, &items)
+    }
+}
+
+/// Returns an equal-sum pair if the pigeonhole principle guarantees one, that is, if the `k`
+/// smallest items have `2^k > k * w_k + 1` subsets for some `k`. The pair is then found among
+/// those items by a dynamic program over their reached sums, which gives up and returns `None`
+/// once more than `2^24` distinct sums would have to be stored.
+pub fn pigeonhole_equal_subsets(input: &[u64]) -> Option<EqualSubsets> {
+    if let Some(trivial) = trivial_equal_subsets(input) {
+        return Some(trivial);
+    }
+    let mut items = (0..input.len())
+        .filter(|&i| input[i] != 0)
+        .collect::<Vec<_>>();
+    items.sort_by_key(|&i| input[i]);
+    let k = pigeonhole_prefix(input, &items)?;
+    first_collision(input, &items[..k])
+}
+
+// Two equal values form a pair of single-item subsets. This includes two zeros, which are a
+// valid answer with sum 0.
+fn trivial_equal_subsets(input: &[u64]) -> Option<EqualSubsets> {
+    let mut seen = HashMap::new();
+    for (i, &x) in input.iter().enumerate() {
+        if let Some(j) = seen.insert(x, i) {
+            return Some(EqualSubsets::new(input, vec![j], vec![i]));
+        }
+    }
+    None
+}
+
+fn pigeonhole_prefix(input: &[u64], items: &[usize]) -> Option<usize> {
+    (1..=items.len().min(63)).find(|&k| {
+        let max = input[items[k - 1]] as u128;
+        1u128 << k > k as u128 * max + 1
+    })
+}
+
+/// The most distinct sums [`first_collision`] stores before giving up.
+const COLLISION_SUMS: usize = 1 << 24;
+
+// Finds two distinct subsets reaching the same sum, the first one to do so in item order. Only
+// the reached sums are stored, so sparse weights cost no more than dense ones.
+fn first_collision(input: &[u64], items: &[usize]) -> Option<EqualSubsets> {
+    // For every reached sum other than 0, the item that first reached it.
+    let mut reached_by = HashMap::new();
+    let mut sums = vec![0];
+    for &item in items {
+        let w = input[item];
+        for i in 0..sums.len() {
+            let s = sums[i];
+            if let Entry::Vacant(entry) = reached_by.entry(s + w) {
+                if sums.len() == COLLISION_SUMS {
+                    return None;
+                }
+                entry.insert(item);
+                sums.push(s + w);
+                continue;
+            }
+            let first = collect_subset(input, &reached_by, s + w);
+            let mut second = collect_subset(input, &reached_by, s);
+            second.push(item);
+            return Some(EqualSubsets::new(
+                input,
+                first
+                    .iter()
+                    .copied()
+                    .filter(|i| !second.contains(i))
+                    .collect(),
+                second
+                    .iter()
+                    .copied()
+                    .filter(|i| !first.contains(i))
+                    .collect(),
+            ));
+        }
+    }
+    None
+}
+
+fn collect_subset(input: &[u64], reached_by: &HashMap<u64, usize>, mut sum: u64) -> Vec<usize> {
+    let mut subset = vec![];
+    while sum != 0 {
+        let item = reached_by[&sum];
+        subset.push(item);
+        sum -= input[item];
+    }
+    subset
+}
+
+// Every item goes to the first side, the second side or neither, encoded in base 3.
+fn side_assignments(input: &[u64], items: &[usize]) -> Vec<(i128, u64)> {
+    let mut assignments = vec![(0i128, 0u64)];
+    let mut power = 1;
+    for &item in items {
+        let w = input[item] as i128;
+        assignments = assignments
+            .into_iter()
+            .flat_map(|(d, code)| [(d, code), (d + w, code + power), (d - w, code + 2 * power)])
+            .collect();
+        power *= 3;
+    }
+    assignments
+}
+
+fn decode_assignment(
+    items: &[usize],
+    mut code: u64,
+    first: &mut Vec<usize>,
+    second: &mut Vec<usize>,
+) {
+    for &item in items {
+        match code % 3 {
+            1 => first.push(item),
+            2 => second.push(item),
+            _ => {}
+        }
+        code /= 3;
+    }
+}
+
+fn meet_in_the_middle(input: &[u64], items: &[usize]) -> Option<EqualSubsets> {
+    let (left, right) = items.split_at(items.len() / 2);
+    let left_differences = side_assignments(input, left)
+        .into_iter()
+        .filter(|&(_, code)| code != 0)
+        .collect::<HashMap<_, _>>();
+    for (d, code) in side_assignments(input, right) {
+        let left_code = match left_differences.get(&-d) {
+            Some(&left_code) => left_code,
+            None if d == 0 && code != 0 => 0,
+            None => continue,
+        };
+        let (mut first, mut second) = (vec![], vec![]);
+        decode_assignment(left, left_code, &mut first, &mut second);
+        decode_assignment(right, code, &mut first, &mut second);
+        return Some(EqualSubsets::new(input, first, second));
+    }
+    None
+}
+
+struct Node {
+    sums: Vec<(u64, u64)>,
+    children: Option<Box<(Node, Node)>>,
+}
+
+// An item `w` contributes `(0, 1)` on the second side, `(w, 0)` on neither and `(2w, 1)` on the
+// first side, so equal sides are exactly the pairs `(sigma, c)` with `c > 0`.
+fn convolution_tree<T: Convoluter>(input: &[u64], items: &[usize]) -> Option<EqualSubsets> {
+    let root = build_node::<T>(input, items);
+    let sigma: u64 = items.iter().map(|&i| input[i]).sum();
+    let &target = root.sums.iter().find(|&&(x, c)| x == sigma && c > 0)?;
+    let (mut first, mut second) = (vec![], vec![]);
+    recover(items, &root, target, &mut first, &mut second);
+    Some(EqualSubsets::new(input, first, second))
+}
+
+fn build_node<T: Convoluter>(input: &[u64], items: &[usize]) -> Node {
+    if let [item] = items {
+        let w = input[*item];
+        return Node {
+            sums: vec![(0, 1), (w, 0), (2 * w, 1)],
+            children: None,
+        };
+    }
+    let (left, right) = items.split_at(items.len() / 2);
+    let (left, right) = (build_node::<T>(input, left), build_node::<T>(input, right));
+    Node {
+        sums: subset_sum_2d::<T>(&left.sums, &right.sums),
+        children: Some(Box::new((left, right))),
+    }
+}
+
+fn recover(
+    items: &[usize],
+    node: &Node,
+    target: (u64, u64),
+    first: &mut Vec<usize>,
+    second: &mut Vec<usize>,
+) {
+    let Some(children) = &node.children else {
+        match target {
+            (0, 1) => second.push(items[0]),
+            (_, 1) => first.push(items[0]),
+            _ => {}
+        }
+        return;
+    };
+    let (left_items, right_items) = items.split_at(items.len() / 2);
+    let right_sums = children.1.sums.iter().collect::<HashSet<_>>();
+    let &left_target = children
+        .0
+        .sums
+        .iter()
+        .find(|&&(x, c)| {
+            x <= target.0 && c <= target.1 && right_sums.contains(&(target.0 - x, target.1 - c))
+        })
+        .unwrap();
+    let right_target = (target.0 - left_target.0, target.1 - left_target.1);
+    recover(left_items, &children.0, left_target, first, second);
+    recover(right_items, &children.1, right_target, first, second);
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::fft::{FFT, NTT};
+
+    use super::*;
+
+    fn verify_equal_subsets(input: &[u64], subsets: &EqualSubsets) {
+        assert!(!subsets.first.is_empty() && !subsets.second.is_empty());
+        assert!(subsets.first.iter().all(|i| !subsets.second.contains(i)));
+        assert_eq!(
+            subsets.first.iter().map(|&i| input[i]).sum::<u64>(),
+            subsets.sum
+        );
+        assert_eq!(
+            subsets.second.iter().map(|&i| input[i]).sum::<u64>(),
+            subsets.sum
+        );
+    }
+
+    fn verify_equal_subset_sum<T: Convoluter>(input: &[u64], exists: bool) {
+        let subsets = equal_subset_sum::<T>(input);
+        assert_eq!(subsets.is_some(), exists, "{input:?}");
+        if let Some(subsets) = subsets {
+            verify_equal_subsets(input, &subsets);
+        }
+        let items = (0..input.len()).collect::<Vec<_>>();
+        if input.len() > 1 && !input.contains(&0) && trivial_equal_subsets(input).is_none() {
+            let tree = convolution_tree::<T>(input, &items);
+            assert_eq!(tree.is_some(), exists);
+            if let Some(subsets) = tree {
+                verify_equal_subsets(input, &subsets);
+            }
+        }
+    }
+
+    // Powers of two have pairwise distinct subset sums.
+    fn powers_of_two(n: u32) -> Vec<u64> {
+        (0..n).map(|i| 1 << i).collect()
+    }
+
+    #[test]
+    fn test_equal_subset_sum_fft() {
+        verify_equal_subset_sum::<FFT>(&[], false);
+        verify_equal_subset_sum::<FFT>(&[0, 3, 0], true);
+        verify_equal_subset_sum::<FFT>(&[5, 9, 5], true);
+        verify_equal_subset_sum::<FFT>(&powers_of_two(10), false);
+        verify_equal_subset_sum::<FFT>(&[3, 5, 11, 21, 44, 88, 180, 359], false);
+        verify_equal_subset_sum::<FFT>(&[3, 5, 11, 21, 40, 85, 170, 359], true);
+        verify_equal_subset_sum::<FFT>(&[1, 2, 4, 8, 16, 32, 64, 128, 256, 500, 1000], true);
+    }
+
+    #[test]
+    fn test_equal_subset_sum_ntt() {
+        verify_equal_subset_sum::<NTT>(&powers_of_two(10), false);
+        verify_equal_subset_sum::<NTT>(&[3, 5, 11, 21, 40, 85, 170, 359], true);
+        verify_equal_subset_sum::<NTT>(&[1, 2, 4, 8, 16, 32, 64, 128, 256, 500, 1000], true);
+    }
+
+    #[test]
+    fn test_pigeonhole_equal_subsets() {
+        let input = (0..40).rev().map(|i| 1000 + i * i).collect::<Vec<_>>();
+        let subsets = pigeonhole_equal_subsets(&input).unwrap();
+        verify_equal_subsets(&input, &subsets);
+        assert_eq!(pigeonhole_equal_subsets(&powers_of_two(30)), None);
+        // The sums of these reach about 2^30, far more than a dense table could hold.
+        let input = (0..32).map(|i| (1 << 25) + i * i).collect::<Vec<_>>();
+        let subsets = pigeonhole_equal_subsets(&input).unwrap();
+        verify_equal_subsets(&input, &subsets);
+        let subsets = equal_subset_sum::<FFT>(&input).unwrap();
+        verify_equal_subsets(&input, &subsets);
+    }
+}
@@ -1,49 +1,111 @@
-use rustfft::{num_complex::Complex, FftPlanner};
+use rustfft::{num_complex::Complex, FftDirection, FftPlanner};
 
 use super::Convoluter;
 
 pub struct ComplexFFT {
-    size: usize,
+    x_size: usize,
+    y_size: usize,
+    // The padded lengths of a column and of a row.
+    column_length: usize,
+    row_length: usize,
     points_a: Vec<Complex<f32>>,
     points_b: Vec<Complex<f32>>,
     planner: FftPlanner<f32>,
 }
 
-impl Convoluter for ComplexFFT {
-    fn new(size: usize) -> Self {
+impl ComplexFFT {
+    fn with_padding(x_size: usize, y_size: usize, column_length: usize, row_length: usize) -> Self {
+        let size = column_length * row_length;
         ComplexFFT {
-            size,
+            x_size,
+            y_size,
+            column_length,
+            row_length,
             points_a: vec![Complex::new(0f32, 0f32); size],
             points_b: vec![Complex::new(0f32, 0f32); size],
             planner: FftPlanner::new(),
         }
     }
+    /// Transforms every row and then every column of a row-major grid. The result is left
+    /// transposed (column-major) when the grid has more than one row, the inverse direction
+    /// expects the same layout and restores row-major order.
+    fn process_2d(&mut self, buffer: &mut Vec<Complex<f32>>, direction: FftDirection) {
+        let (column_length, row_length) = (self.column_length, self.row_length);
+        let rows = self.planner.plan_fft(row_length, direction);
+        let columns = self.planner.plan_fft(column_length, direction);
+        match direction {
+            FftDirection::Forward => {
+                rows.process(buffer);
+                if column_length > 1 {
+                    *buffer = transpose(buffer, column_length, row_length);
+                    columns.process(buffer);
+                }
+            }
+            FftDirection::Inverse => {
+                if column_length > 1 {
+                    columns.process(buffer);
+                    *buffer = transpose(buffer, row_length, column_length);
+                }
+                rows.process(buffer);
+            }
+        }
+    }
+}
+
+impl Convoluter for ComplexFFT {
+    fn new(size: usize) -> Self {
+        Self::with_padding(1, size, 1, size)
+    }
+    // Sums along an axis reach up to twice its size, so each axis is zero padded to hold them
+    // instead of wrapping them around onto the grid.
+    fn new_2d(x_size: usize, y_size: usize) -> Self {
+        let padded = |size: usize| (2 * size).saturating_sub(1).max(1);
+        Self::with_padding(x_size, y_size, padded(x_size), padded(y_size))
+    }
     fn convolute_characteristic_vecs(mut self, a: &[bool], b: &[bool]) -> Vec<bool> {
+        let size = self.column_length * self.row_length;
+        if self.x_size * self.y_size == 0 {
+            return vec![];
+        }
+        let (y_size, row_length) = (self.y_size, self.row_length);
         for (i, val) in a.iter().enumerate() {
-            self.points_a[i] = Complex::new(if *val { 1.0 } else { 0.0 }, 0f32);
+            self.points_a[i / y_size * row_length + i % y_size] =
+                Complex::new(if *val { 1.0 } else { 0.0 }, 0f32);
         }
 
         for (i, val) in b.iter().enumerate() {
-            self.points_b[i] = Complex::new(if *val { 1.0 } else { 0.0 }, 0f32);
+            self.points_b[i / y_size * row_length + i % y_size] =
+                Complex::new(if *val { 1.0 } else { 0.0 }, 0f32);
         }
 
-        let fft = self.planner.plan_fft_forward(self.size);
-        fft.process(&mut self.points_a);
-        fft.process(&mut self.points_b);
+        let mut points_a = std::mem::take(&mut self.points_a);
+        let mut points_b = std::mem::take(&mut self.points_b);
+        self.process_2d(&mut points_a, FftDirection::Forward);
+        self.process_2d(&mut points_b, FftDirection::Forward);
 
-        for (i, val) in self.points_b.iter().enumerate() {
-            self.points_a[i] *= val;
+        for (i, val) in points_b.iter().enumerate() {
+            points_a[i] *= val;
         }
 
-        let fft_inv = self.planner.plan_fft_inverse(self.size);
-        fft_inv.process(&mut self.points_a);
+        self.process_2d(&mut points_a, FftDirection::Inverse);
 
         // The 0.9 is a hack to avoid floating point errors, it should theoretically be 1.0
-        let product_coefficients: Vec<bool> = self
-            .points_a
-            .iter()
-            .map(|c| c.re >= self.size as f32 * 0.95)
+        let product_coefficients: Vec<bool> = points_a
+            .chunks_exact(row_length)
+            .take(self.x_size)
+            .flat_map(|row| row.iter().take(y_size))
+            .map(|c| c.re >= size as f32 * 0.95)
             .collect();
         product_coefficients
     }
 }
+
+fn transpose(buffer: &[Complex<f32>], rows: usize, columns: usize) -> Vec<Complex<f32>> {
+    let mut transposed = Vec::with_capacity(buffer.len());
+    for column in 0..columns {
+        for row in 0..rows {
+            transposed.push(buffer[row * columns + column]);
+        }
+    }
+    transposed
+}
@@ -4,7 +4,13 @@ pub mod number_theoretic;
 pub use complex::ComplexFFT as FFT;
 pub use number_theoretic::NumberTheoreticTransform as NTT;
 pub trait Convoluter {
+    /// Creates a convoluter for vectors of `size`, which has to hold every sum, as the
+    /// convolution is cyclic.
     fn new(size: usize) -> Self;
+    /// Creates a convoluter for row-major `x_size` by `y_size` grids. Each axis is transformed
+    /// separately and zero padded, so sums beyond the grid on either axis are dropped instead of
+    /// wrapping around onto it.
+    fn new_2d(x_size: usize, y_size: usize) -> Self;
     fn convolute_characteristic_vecs(self, a: &[bool], b: &[bool]) -> Vec<bool>;
 }
 #[cfg(test)]
@@ -17,8 +23,54 @@ mod tests {
         assert_eq!(fft, ntt);
     }
 
+    fn verify_match_2d(a: &[bool], b: &[bool], x_size: usize, y_size: usize) {
+        let fft = FFT::new_2d(x_size, y_size).convolute_characteristic_vecs(a, b);
+        let ntt = NTT::new_2d(x_size, y_size).convolute_characteristic_vecs(a, b);
+        assert_eq!(fft, ntt);
+        let mut expected = vec![false; x_size * y_size];
+        for (i, _) in a.iter().enumerate().filter(|(_, &v)| v) {
+            for (j, _) in b.iter().enumerate().filter(|(_, &v)| v) {
+                let (x, y) = (i / y_size + j / y_size, i % y_size + j % y_size);
+                // Sums beyond the grid are dropped, not wrapped around onto it.
+                if x < x_size && y < y_size {
+                    expected[x * y_size + y] = true;
+                }
+            }
+        }
+        assert_eq!(fft, expected);
+    }
+
     #[test]
     fn test_fft() {
         verify_match(&[true, false, true, false], &[true, false, false, true]);
     }
+
+    #[test]
+    fn test_fft_2d() {
+        verify_match_2d(
+            &[true, false, true, false, false, false, false, false],
+            &[false, true, false, false, false, false, false, false],
+            2,
+            4,
+        );
+        verify_match_2d(
+            &[true, false, false, false, true, false, false, false, false],
+            &[true, true, false, true, false, false, false, false, false],
+            3,
+            3,
+        );
+        // Sums reach past the edge of both axes.
+        let corners = |x_size: usize, y_size: usize| {
+            let mut grid = vec![false; x_size * y_size];
+            for x in [0, x_size - 1] {
+                for y in [0, y_size - 1] {
+                    grid[x * y_size + y] = true;
+                }
+            }
+            grid
+        };
+        verify_match_2d(&corners(4, 3), &corners(4, 3), 4, 3);
+        verify_match_2d(&corners(5, 17), &corners(5, 17), 5, 17);
+        verify_match_2d(&corners(1, 6), &corners(1, 6), 1, 6);
+    }
 }
@@ -3,41 +3,120 @@ use concrete_ntt::prime64::Plan;
 use super::Convoluter;
 
 pub struct NumberTheoreticTransform {
-    size: usize,
+    x_size: usize,
+    y_size: usize,
     points_a: Vec<u64>,
     points_b: Vec<u64>,
-    planner: Plan,
+    rows: Plan,
+    columns: Option<Plan>,
 }
 
 // const PRIME: u64 = 9807971461541688691;
 const PRIME: u64 = 2013265921;
 
-impl Convoluter for NumberTheoreticTransform {
-    fn new(size: usize) -> Self {
-        let pow_2_size = size.next_power_of_two().max(16);
+impl NumberTheoreticTransform {
+    // The plans compute negacyclic convolutions, so every axis is padded to a power of two that
+    // holds the whole linear convolution along it.
+    fn padded_size(size: usize) -> usize {
+        size.next_power_of_two().max(16)
+    }
+
+    fn with_padding(x_size: usize, y_size: usize, column_length: usize, row_length: usize) -> Self {
+        let columns = (column_length > 1).then(|| Plan::try_new(column_length, PRIME).unwrap());
         NumberTheoreticTransform {
-            planner: Plan::try_new(pow_2_size, PRIME).unwrap(),
-            size,
-            points_a: vec![0; pow_2_size],
-            points_b: vec![0; pow_2_size],
+            rows: Plan::try_new(row_length, PRIME).unwrap(),
+            columns,
+            x_size,
+            y_size,
+            points_a: vec![0; row_length * column_length],
+            points_b: vec![0; row_length * column_length],
+        }
+    }
+
+    fn row_length(&self) -> usize {
+        self.rows.ntt_size()
+    }
+
+    fn column_length(&self) -> usize {
+        self.columns.as_ref().map_or(1, Plan::ntt_size)
+    }
+
+    fn fwd_2d(&self, buffer: &mut [u64]) {
+        for row in buffer.chunks_exact_mut(self.row_length()) {
+            self.rows.fwd(row);
+        }
+        if let Some(columns) = &self.columns {
+            self.for_each_column(buffer, |column| columns.fwd(column));
         }
     }
+
+    fn inv_2d(&self, buffer: &mut [u64]) {
+        if let Some(columns) = &self.columns {
+            self.for_each_column(buffer, |column| {
+                columns.inv(column);
+                columns.normalize(column);
+            });
+        }
+        for row in buffer.chunks_exact_mut(self.row_length()) {
+            self.rows.inv(row);
+        }
+    }
+
+    fn for_each_column(&self, buffer: &mut [u64], mut f: impl FnMut(&mut [u64])) {
+        let (row_length, column_length) = (self.row_length(), self.column_length());
+        let mut column = vec![0; column_length];
+        for y in 0..row_length {
+            for (x, value) in column.iter_mut().enumerate() {
+                *value = buffer[x * row_length + y];
+            }
+            f(&mut column);
+            for (x, value) in column.iter().enumerate() {
+                buffer[x * row_length + y] = *value;
+            }
+        }
+    }
+}
+
+impl Convoluter for NumberTheoreticTransform {
+    fn new(size: usize) -> Self {
+        Self::with_padding(1, size, 1, Self::padded_size(size))
+    }
+    // Sums along an axis reach up to twice its size, so each axis is padded to hold them instead
+    // of wrapping them around onto the grid.
+    fn new_2d(x_size: usize, y_size: usize) -> Self {
+        let column_length = if x_size > 1 {
+            Self::padded_size(2 * x_size - 1)
+        } else {
+            1
+        };
+        let row_length = Self::padded_size((2 * y_size).saturating_sub(1));
+        Self::with_padding(x_size, y_size, column_length, row_length)
+    }
     fn convolute_characteristic_vecs(mut self, a: &[bool], b: &[bool]) -> Vec<bool> {
+        let row_length = self.row_length();
         for (i, val) in a.iter().enumerate() {
-            self.points_a[i] = if *val { 1 } else { 0 };
+            self.points_a[i / self.y_size * row_length + i % self.y_size] = u64::from(*val);
         }
         for (i, val) in b.iter().enumerate() {
-            self.points_b[i] = if *val { 1 } else { 0 };
-        }
-        self.planner.fwd(&mut self.points_a);
-        self.planner.fwd(&mut self.points_b);
-        self.planner
-            .mul_assign_normalize(&mut self.points_a, &self.points_b);
-        self.planner.inv(&mut self.points_a);
-        self.points_a
-            .into_iter()
-            .take(self.size)
-            .map(|x| x != 0)
+            self.points_b[i / self.y_size * row_length + i % self.y_size] = u64::from(*val);
+        }
+        let (mut points_a, mut points_b) = (
+            std::mem::take(&mut self.points_a),
+            std::mem::take(&mut self.points_b),
+        );
+        self.fwd_2d(&mut points_a);
+        self.fwd_2d(&mut points_b);
+        for (row_a, row_b) in points_a
+            .chunks_exact_mut(row_length)
+            .zip(points_b.chunks_exact(row_length))
+        {
+            self.rows.mul_assign_normalize(row_a, row_b);
+        }
+        self.inv_2d(&mut points_a);
+        points_a
+            .chunks_exact(row_length)
+            .take(self.x_size)
+            .flat_map(|row| row.iter().take(self.y_size).map(|&x| x != 0))
             .collect()
     }
 }
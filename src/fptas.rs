@@ -0,0 +1,151 @@
+//! Textbook subset-sum FPTAS algorithms, kept as baselines to compare the sumset approximation
+//! against. The partition variants share the signature of
+//! [`approximate_partition`](crate::approximate_partition).
+
+use crate::approximation::Epsilon;
+
+/// Approximates the largest subset sum not exceeding `t` with the Gens–Levner algorithm, within
+/// `epsilon * t` of the optimum.
+///
+/// `[0, t]` is cut into intervals of length `epsilon * t`, and the dynamic program keeps only the
+/// smallest and the largest reachable sum of every interval. Runs in `O(n / epsilon)`.
+pub fn gens_levner_subset_sum(input: &[u64], t: u64, epsilon: impl Into<Epsilon>) -> u64 {
+    let width = epsilon.into().floor_mul(t, 1).max(1);
+    interval_sums(input, t, width)
+        .into_iter()
+        .max()
+        .unwrap_or(0)
+}
+
+/// Approximates the largest subset sum not exceeding `t` with the algorithm of Kellerer, Mansini,
+/// Pferschy and Speranza, within `epsilon * t` of the optimum.
+///
+/// Items above `epsilon * t / 2` are large. Of the large items in `(j * w, (j + 1) * w]` at most
+/// `t / (j * w)` fit together, so only that many of the smallest and of the largest are kept.
+/// Their sums go through the interval dynamic program of [`gens_levner_subset_sum`], and every
+/// kept sum is then filled up greedily with the small items.
+pub fn kellerer_subset_sum(input: &[u64], t: u64, epsilon: impl Into<Epsilon>) -> u64 {
+    let width = epsilon.into().floor_mul(t, 2).max(1);
+    let (small, mut large): (Vec<u64>, Vec<u64>) = input
+        .iter()
+        .copied()
+        .filter(|&x| x <= t)
+        .partition(|&x| x <= width);
+    large.sort_unstable();
+    let mut reduced = vec![];
+    for class in large.chunk_by(|a, b| a / width == b / width) {
+        let keep = (t / (class[0] / width * width)) as usize;
+        if class.len() <= 2 * keep {
+            reduced.extend_from_slice(class);
+        } else {
+            reduced.extend_from_slice(&class[..keep]);
+            reduced.extend_from_slice(&class[class.len() - keep..]);
+        }
+    }
+    let mut filled = 0;
+    let prefix_sums = small
+        .iter()
+        .map(|&x| {
+            filled += x;
+            filled
+        })
+        .collect::<Vec<_>>();
+    interval_sums(&reduced, t, width)
+        .into_iter()
+        .map(|sum| {
+            let fitting = prefix_sums.partition_point(|&prefix| prefix <= t - sum);
+            sum + fitting.checked_sub(1).map_or(0, |i| prefix_sums[i])
+        })
+        .max()
+        .unwrap_or(0)
+}
+
+// Returns the smallest and largest reachable sum of every interval of `width` in `[0, t]`.
+fn interval_sums(input: &[u64], t: u64, width: u64) -> Vec<u64> {
+    let mut intervals: Vec<Option<(u64, u64)>> = vec![None; (t / width) as usize + 1];
+    intervals[0] = Some((0, 0));
+    for &x in input.iter().filter(|&&x| x <= t) {
+        let current = intervals.iter().flatten().copied().collect::<Vec<_>>();
+        for (low, high) in current {
+            for sum in [low + x, high + x].into_iter().filter(|&sum| sum <= t) {
+                let interval = &mut intervals[(sum / width) as usize];
+                *interval = Some(match *interval {
+                    Some((low, high)) => (low.min(sum), high.max(sum)),
+                    None => (sum, sum),
+                });
+            }
+        }
+    }
+    intervals
+        .into_iter()
+        .flatten()
+        .flat_map(|(low, high)| [low, high])
+        .collect()
+}
+
+/// Approximates the partition value with [`gens_levner_subset_sum`].
+pub fn gens_levner_partition(input: &[u16], epsilon: impl Into<Epsilon>) -> f64 {
+    let (input, t) = partition_instance(input);
+    gens_levner_subset_sum(&input, t, epsilon) as f64
+}
+
+/// Approximates the partition value with [`kellerer_subset_sum`].
+pub fn kellerer_partition(input: &[u16], epsilon: impl Into<Epsilon>) -> f64 {
+    let (input, t) = partition_instance(input);
+    kellerer_subset_sum(&input, t, epsilon) as f64
+}
+
+fn partition_instance(input: &[u16]) -> (Vec<u64>, u64) {
+    let input = input.iter().map(|&x| x as u64).collect::<Vec<_>>();
+    let t = input.iter().sum::<u64>() / 2;
+    (input, t)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{helpers::dynamic_programing_partition, meet_in_the_middle::exact_subset_sum};
+
+    use super::*;
+
+    type SubsetSum = fn(&[u64], u64, Epsilon) -> u64;
+
+    fn verify_subset_sum(algorithm: SubsetSum, input: &[u64], epsilon: Epsilon) {
+        let sigma: u64 = input.iter().sum();
+        for t in [0, 1, sigma / 7, sigma / 3, sigma / 2, sigma] {
+            let opt = exact_subset_sum(input, t as u128).unwrap().sum as u64;
+            let approximation = algorithm(input, t, epsilon);
+            assert!(approximation <= opt);
+            assert!(opt - approximation <= epsilon.floor_mul(t, 1));
+        }
+    }
+
+    #[test]
+    fn test_subset_sum_baselines() {
+        let input = [
+            1001, 2000, 3000, 4000, 5000, 6000, 7000, 1000, 1001, 1002, 1003, 5, 7, 9,
+        ];
+        let algorithms: [SubsetSum; 2] = [gens_levner_subset_sum, kellerer_subset_sum];
+        for algorithm in algorithms {
+            for epsilon in [2, 10, 100, 1000].map(Epsilon::from_inverse) {
+                verify_subset_sum(algorithm, &input, epsilon);
+                verify_subset_sum(algorithm, &[], epsilon);
+                verify_subset_sum(algorithm, &[3, 3, 3, 3, 3, 3, 3, 3, 3, 3, 3, 4], epsilon);
+            }
+        }
+    }
+
+    #[test]
+    fn test_partition_baselines() {
+        let input = (0..60).map(|x| 1000 + x * x).collect::<Vec<u16>>();
+        let opt =
+            dynamic_programing_partition(&input.iter().map(|&x| x as u64).collect::<Vec<_>>())
+                .unwrap();
+        let t = input.iter().map(|&x| x as u64).sum::<u64>() / 2;
+        let algorithms: [fn(&[u16], f64) -> f64; 2] = [gens_levner_partition, kellerer_partition];
+        for algorithm in algorithms {
+            let approximation = algorithm(&input, 0.01);
+            assert!(approximation <= opt as f64);
+            assert!(opt as f64 - approximation <= 0.01 * t as f64);
+        }
+    }
+}
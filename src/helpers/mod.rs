@@ -8,6 +8,14 @@ pub const fn ceil_div(a: u64, b: u64) -> u64 {
     (a + b - 1) / b
 }
 
+pub fn gcd(a: u64, b: u64) -> u64 {
+    if b == 0 {
+        a
+    } else {
+        gcd(b, a % b)
+    }
+}
+
 pub struct PowerOfTwoIterator {
     current: u64,
     limit: u64,
@@ -38,16 +46,28 @@ impl Iterator for PowerOfTwoIterator {
     }
 }
 
+pub fn count_multiplicities<T: Copy + Ord>(set: &[T]) -> BTreeMap<T, usize> {
+    let mut map = BTreeMap::new();
+    for i in set {
+        *map.entry(*i).or_insert(0_usize) += 1;
+    }
+    map
+}
+
 pub fn reduce_multiplicity<T>(set: &[T]) -> BTreeMap<T, usize>
 where
     T: Copy + Ord + Add<Output = T>,
 {
-    let mut map = BTreeMap::new();
+    reduce_multiplicities(&count_multiplicities(set))
+}
+
+/// [`reduce_multiplicity`] for a multiset that is already counted.
+pub fn reduce_multiplicities<T>(counts: &BTreeMap<T, usize>) -> BTreeMap<T, usize>
+where
+    T: Copy + Ord + Add<Output = T>,
+{
     let mut new_map = BTreeMap::new();
-    for i in set {
-        *map.entry(*i).or_insert(0_usize) += 1;
-    }
-    for (&key, &mult) in map.iter() {
+    for (&key, &mult) in counts.iter() {
         reduce_single_element(key, mult, &mut new_map);
     }
     new_map
@@ -76,10 +96,12 @@ where
     reduce_single_element(number + number, mult, map);
 }
 
-use std::collections::HashSet;
+use std::collections::BTreeSet;
 
+/// Every nonempty subset sum of `vec`, plus 0 if `vec` contains it, sorted and without
+/// duplicates.
 pub fn naive_sumset(vec: &[u64]) -> Vec<u64> {
-    let mut result = HashSet::new();
+    let mut result = BTreeSet::new();
     generate_sumset(vec, 0, 0, &mut result);
     if !vec.contains(&0) {
         result.remove(&0);
@@ -87,7 +109,7 @@ pub fn naive_sumset(vec: &[u64]) -> Vec<u64> {
     result.into_iter().collect()
 }
 
-fn generate_sumset(vec: &[u64], index: usize, current_sum: u64, result: &mut HashSet<u64>) {
+fn generate_sumset(vec: &[u64], index: usize, current_sum: u64, result: &mut BTreeSet<u64>) {
     if index == vec.len() {
         result.insert(current_sum);
         return;
@@ -96,35 +118,12 @@ fn generate_sumset(vec: &[u64], index: usize, current_sum: u64, result: &mut Has
     generate_sumset(vec, index + 1, current_sum, result);
 }
 
-pub fn dynamic_programing_partition(set: &[u64]) -> u64 {
-    let sum = set.iter().sum::<u64>() / 2;
-    let mut prev = vec![false; sum as usize + 1];
-
-    prev[0] = true;
-
-    for i in 1..=sum as usize {
-        prev[i] = false;
-    }
-
-    let mut curr = vec![false; sum as usize + 1];
-
-    for i in 1..=set.len() {
-        for j in 1..=sum as usize {
-            if j < set[i - 1] as usize {
-                curr[j] = prev[j];
-            } else {
-                curr[j] = prev[j] || prev[j - set[i - 1] as usize];
-            }
-        }
-        std::mem::swap(&mut prev, &mut curr);
-    }
-
-    return prev
-        .iter()
-        .enumerate()
-        .filter_map(|(i, &x)| if x { Some(i as u64) } else { None })
-        .max()
-        .unwrap_or(0);
+/// The largest subset sum not exceeding half of the total, by
+/// [`bitset_subset_sum`](crate::dynamic_programing::bitset_subset_sum). `None` when the total
+/// does not fit in a `u64` or its table does not fit in memory.
+pub fn dynamic_programing_partition(set: &[u64]) -> Option<u64> {
+    let sigma = set.iter().try_fold(0u64, |sum, &x| sum.checked_add(x))?;
+    crate::dynamic_programing::bitset_subset_sum(set, sigma / 2).ok()
 }
 
 #[cfg(test)]
@@ -157,23 +156,32 @@ mod tests {
     }
 
     #[test]
+    #[allow(clippy::useless_vec)]
     fn test_dynamic_programing_partition() {
-        assert_eq!(dynamic_programing_partition(&vec![1, 2, 3, 4, 5]), 7);
+        assert_eq!(dynamic_programing_partition(&vec![1, 2, 3, 4, 5]), Some(7));
 
-        assert_eq!(dynamic_programing_partition(&vec![1, 2, 3, 4, 5, 6]), 10);
+        assert_eq!(
+            dynamic_programing_partition(&vec![1, 2, 3, 4, 5, 6]),
+            Some(10)
+        );
 
-        assert_eq!(dynamic_programing_partition(&vec![1, 2, 3, 4, 5, 6, 7]), 14);
+        assert_eq!(
+            dynamic_programing_partition(&vec![1, 2, 3, 4, 5, 6, 7]),
+            Some(14)
+        );
 
         assert_eq!(
             dynamic_programing_partition(&vec![1, 2, 3, 4, 5, 6, 7, 8]),
-            18
+            Some(18)
         );
 
         assert_eq!(
             dynamic_programing_partition(&vec![1, 2, 3, 4, 5, 6, 7, 8, 9]),
-            22
+            Some(22)
         );
 
-        assert_eq!(dynamic_programing_partition(&vec![2; 1000]), 1000);
+        assert_eq!(dynamic_programing_partition(&vec![2; 1000]), Some(1000));
+
+        assert_eq!(dynamic_programing_partition(&vec![u64::MAX, 1]), None);
     }
 }
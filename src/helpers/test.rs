@@ -1,25 +1,29 @@
+use crate::{approximation::Epsilon, verify};
+
+// The multiplicative slack as an exact `1 / k`, rounded down, or none for `0`.
+fn multiplicative(delta_mul: f64) -> Option<Epsilon> {
+    (delta_mul > 0.0).then(|| Epsilon::at_most(delta_mul))
+}
+
 pub fn verify_approximation(
     approximation: &[u64],
     expected: &[u64],
     delta_mul: f64,
     delta_add: u64,
 ) {
-    for b in expected.iter() {
-        verify_element_in_approximation(approximation, *b, delta_mul, delta_add);
-    }
-    for a in approximation.iter() {
-        assert!(
-            expected
-                .iter()
-                .filter(|&b| a <= b)
-                .any(|b| ((1f64 - delta_mul) * (*b as f64)) as u64 <= *a + delta_add),
-            "{:?} (1-{:?}, {:?})-approximation not found in (expected) {:?}",
-            a,
-            delta_mul,
-            delta_add,
-            expected
-        );
-    }
+    let report = verify::verify_approximation(
+        approximation,
+        expected,
+        multiplicative(delta_mul),
+        delta_add,
+    );
+    assert!(
+        report.is_valid(),
+        "not a (1-{:?}, {:?})-approximation: {}",
+        delta_mul,
+        delta_add,
+        report
+    );
 }
 
 pub fn verify_element_in_approximation(
@@ -29,10 +33,12 @@ pub fn verify_element_in_approximation(
     delta_add: u64,
 ) {
     assert!(
-        approximation
-            .iter()
-            .filter(|&a| *a <= expected_element)
-            .any(|a| ((1f64 - delta_mul) * (expected_element as f64)) as u64 <= *a + delta_add),
+        verify::is_approximated(
+            approximation,
+            expected_element,
+            multiplicative(delta_mul),
+            delta_add
+        ),
         "{:?} (actual) not found in approximation: {:?}",
         expected_element,
         approximation
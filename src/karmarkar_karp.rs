@@ -0,0 +1,263 @@
+use std::{
+    collections::BinaryHeap,
+    time::{Duration, Instant},
+};
+
+use crate::meet_in_the_middle::SubsetWitness;
+
+/// Differencing merges two groups of items into one, either placing them on opposite sides
+/// (their difference) or on the same side (their sum). The merges form a forest over the items,
+/// from which the sides are read back.
+#[derive(Default)]
+struct Merges {
+    items: usize,
+    // For every merged group, its two parts and whether they are on opposite sides.
+    groups: Vec<(usize, usize, bool)>,
+}
+
+impl Merges {
+    fn merge(&mut self, a: usize, b: usize, opposite: bool) -> usize {
+        self.groups.push((a, b, opposite));
+        self.items + self.groups.len() - 1
+    }
+
+    // Collects the items whose side differs from the side of their root.
+    fn smaller_side(&self, input: &[u64], roots: &[(usize, bool)]) -> SubsetWitness {
+        let mut sides = (vec![], vec![]);
+        let mut stack = roots.to_vec();
+        while let Some((group, flipped)) = stack.pop() {
+            if group < self.items {
+                if flipped { &mut sides.1 } else { &mut sides.0 }.push(group);
+                continue;
+            }
+            let (a, b, opposite) = self.groups[group - self.items];
+            stack.push((a, flipped));
+            stack.push((b, flipped ^ opposite));
+        }
+        let sum = |side: &[usize]| side.iter().map(|&i| input[i] as u128).sum::<u128>();
+        let mut items = if sum(&sides.0) <= sum(&sides.1) {
+            sides.0
+        } else {
+            sides.1
+        };
+        items.sort_unstable();
+        SubsetWitness {
+            sum: sum(&items),
+            items,
+        }
+    }
+}
+
+/// Partitions `input` with the Karmarkar–Karp largest differencing heuristic: the two largest
+/// numbers are repeatedly replaced by their difference, which commits them to opposite sides.
+/// Returns the smaller side. Runs in `O(n log n)`, without any guarantee.
+pub fn karmarkar_karp(input: &[u64]) -> SubsetWitness {
+    let mut merges = Merges {
+        items: input.len(),
+        ..Default::default()
+    };
+    let mut heap = input
+        .iter()
+        .enumerate()
+        .map(|(i, &x)| (x as u128, i))
+        .collect::<BinaryHeap<_>>();
+    while heap.len() > 1 {
+        let (a, i) = heap.pop().unwrap();
+        let (b, j) = heap.pop().unwrap();
+        heap.push((a - b, merges.merge(i, j, true)));
+    }
+    let roots = heap.iter().map(|&(_, i)| (i, false)).collect::<Vec<_>>();
+    merges.smaller_side(input, &roots)
+}
+
+/// Limits on the search of [`complete_karmarkar_karp`].
+#[derive(Debug, Clone, Copy, Default)]
+pub struct SearchLimits {
+    /// The largest number of search nodes to visit.
+    pub nodes: Option<u64>,
+    /// The longest time to search for.
+    pub time: Option<Duration>,
+}
+
+/// The best partition found by [`complete_karmarkar_karp`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CompleteKarmarkarKarp {
+    /// The smaller side.
+    pub witness: SubsetWitness,
+    /// Whether the search finished, which proves the partition optimal.
+    pub optimal: bool,
+    pub nodes: u64,
+}
+
+/// Partitions `input` with the complete Karmarkar–Karp algorithm. The two largest numbers are
+/// replaced by their difference first and by their sum second, so the first leaf is the
+/// Karmarkar–Karp partition, and the search continues as an anytime branch and bound. A branch
+/// ends once its largest number outweighs all others, and the search once the sides are as equal
+/// as the parity of the total allows or a limit is reached.
+pub fn complete_karmarkar_karp(input: &[u64], limits: SearchLimits) -> CompleteKarmarkarKarp {
+    let mut numbers = input
+        .iter()
+        .enumerate()
+        .map(|(i, &x)| (x as u128, i))
+        .collect::<Vec<_>>();
+    numbers.sort_unstable_by(|a, b| b.cmp(a));
+    let sigma: u128 = numbers.iter().map(|&(x, _)| x).sum();
+    let mut search = Search {
+        input,
+        merges: Merges {
+            items: input.len(),
+            ..Default::default()
+        },
+        limits,
+        start: Instant::now(),
+        nodes: 0,
+        stopped: false,
+        perfect: sigma % 2,
+        best: None,
+    };
+    search.search(numbers);
+    let (_, witness) = search.best.unwrap_or_else(|| {
+        (
+            sigma,
+            SubsetWitness {
+                items: vec![],
+                sum: 0,
+            },
+        )
+    });
+    CompleteKarmarkarKarp {
+        witness,
+        optimal: !search.stopped,
+        nodes: search.nodes,
+    }
+}
+
+struct Search<'a> {
+    input: &'a [u64],
+    merges: Merges,
+    limits: SearchLimits,
+    start: Instant,
+    nodes: u64,
+    stopped: bool,
+    perfect: u128,
+    // The smallest difference found, with the smaller side.
+    best: Option<(u128, SubsetWitness)>,
+}
+
+impl Search<'_> {
+    fn done(&mut self) -> bool {
+        if self
+            .best
+            .as_ref()
+            .is_some_and(|(best, _)| *best == self.perfect)
+        {
+            return true;
+        }
+        let out_of_nodes = self.limits.nodes.is_some_and(|limit| self.nodes >= limit);
+        let out_of_time = self.nodes % 1024 == 0
+            && self
+                .limits
+                .time
+                .is_some_and(|limit| self.start.elapsed() >= limit);
+        self.stopped |= out_of_nodes || out_of_time;
+        self.stopped
+    }
+
+    fn record(&mut self, difference: u128, roots: &[(usize, bool)]) {
+        if self
+            .best
+            .as_ref()
+            .map_or(true, |(best, _)| difference < *best)
+        {
+            self.best = Some((difference, self.merges.smaller_side(self.input, roots)));
+        }
+    }
+
+    // `numbers` is sorted in decreasing order.
+    fn search(&mut self, numbers: Vec<(u128, usize)>) {
+        if self.best.is_some() && self.done() {
+            return;
+        }
+        self.nodes += 1;
+        let Some(&(largest, group)) = numbers.first() else {
+            return;
+        };
+        let rest: u128 = numbers[1..].iter().map(|&(x, _)| x).sum();
+        if largest >= rest {
+            let roots = [(group, false)]
+                .into_iter()
+                .chain(numbers[1..].iter().map(|&(_, i)| (i, true)))
+                .collect::<Vec<_>>();
+            self.record(largest - rest, &roots);
+            return;
+        }
+        let (b, other) = numbers[1];
+        for (value, opposite) in [(largest - b, true), (largest + b, false)] {
+            let merged = self.merges.merge(group, other, opposite);
+            let mut next = numbers[2..].to_vec();
+            let position = next.partition_point(|&(x, _)| x > value);
+            next.insert(position, (value, merged));
+            self.search(next);
+            self.merges.groups.pop();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::meet_in_the_middle::exact_partition;
+
+    use super::*;
+
+    fn verify_witness(input: &[u64], witness: &SubsetWitness) {
+        let sigma: u128 = input.iter().map(|&x| x as u128).sum();
+        let sum: u128 = witness.items.iter().map(|&i| input[i] as u128).sum();
+        assert_eq!(sum, witness.sum);
+        assert!(2 * witness.sum <= sigma);
+    }
+
+    #[test]
+    fn test_karmarkar_karp() {
+        // Differencing 8, 7, 6, 5, 4 ends in a difference of 2, while 15 and 15 is optimal.
+        let input = [8, 7, 6, 5, 4];
+        let witness = karmarkar_karp(&input);
+        verify_witness(&input, &witness);
+        assert_eq!(witness.sum, 14);
+        assert_eq!(karmarkar_karp(&[]).sum, 0);
+        assert_eq!(karmarkar_karp(&[5]).sum, 0);
+    }
+
+    #[test]
+    fn test_complete_karmarkar_karp() {
+        let inputs = [
+            vec![8, 7, 6, 5, 4],
+            vec![
+                1001, 2000, 3000, 4000, 5000, 6000, 7000, 8000, 9000, 1000, 1001, 1002, 5,
+            ],
+            (0..20).map(|i| 1000 + i * i * i).collect(),
+            vec![u64::MAX, u64::MAX, 1],
+        ];
+        for input in inputs {
+            let result = complete_karmarkar_karp(&input, SearchLimits::default());
+            verify_witness(&input, &result.witness);
+            assert!(result.optimal);
+            assert_eq!(result.witness.sum, exact_partition(&input).unwrap().sum);
+        }
+    }
+
+    #[test]
+    fn test_complete_karmarkar_karp_limits() {
+        let input = (1..=40u64)
+            .map(|i| i.wrapping_mul(0x9e37_79b9_7f4a_7c15) >> 24)
+            .collect::<Vec<_>>();
+        let limits = SearchLimits {
+            nodes: Some(100),
+            time: None,
+        };
+        let result = complete_karmarkar_karp(&input, limits);
+        verify_witness(&input, &result.witness);
+        assert!(!result.optimal);
+        assert!(result.nodes <= 100);
+        assert!(result.witness.sum >= karmarkar_karp(&input).sum);
+    }
+}
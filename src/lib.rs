@@ -1,34 +1,120 @@
+pub mod anytime;
 pub mod approximation;
 pub mod characteristic;
+pub mod context;
+pub mod dynamic_programing;
+pub mod equal_subset_sum;
 pub mod fft;
+pub mod fptas;
 pub mod helpers;
+pub mod karmarkar_karp;
+pub mod meet_in_the_middle;
+pub mod portfolio;
+pub mod preprocess;
+pub mod solver;
+pub mod split;
 pub mod subset_sum;
+pub mod verify;
 
-pub use approximation::approximate_sumset;
+pub use approximation::{approximate_sumset, ApproximateSumset, Epsilon};
 pub use fft::{Convoluter, FFT, NTT};
 
-pub fn approximate_partition<T: Convoluter>(input: &[u16], epsilon: f64) -> f64 {
-    let approximation = approximate_sumset::<T>(input, epsilon);
-    let sigma: u64 = input.iter().copied().map(u64::from).sum();
-    let t = (sigma / 2) as f64;
-    let a = approximation
-        .into_iter()
-        .filter(|&x| x as f64 <= t)
-        .max()
-        .unwrap_or(0) as f64;
-    let compilment = t * (1.0 - epsilon / 2.0);
-    a.min(compilment)
+use approximation::Side;
+use split::{approximate_pinned_split, Bin, Ratio, Split, SplitError};
+
+/// Approximates the largest subset sum not exceeding half of the total. Inputs whose `2^(n/2)`
+/// half-sums are no more than the `min(sigma, n / epsilon)` sums an approximation may hold are
+/// solved exactly by [`exact_partition`](meet_in_the_middle::exact_partition) instead, since
+/// the approximation would be coarser without being cheaper there. The answer is never worse
+/// than the [`karmarkar_karp`](karmarkar_karp::karmarkar_karp) partition. The input is reduced
+/// by [`preprocess`](preprocess::preprocess) first.
+///
+/// An `epsilon` that is not of the form `1 / k` is rounded down to one, so `0.3` runs with `1 / 4`,
+/// see [`Epsilon::at_most`].
+pub fn approximate_partition<T: Convoluter>(input: &[u16], epsilon: impl Into<Epsilon>) -> f64 {
+    let values = input.iter().map(|&x| x as u64).collect::<Vec<_>>();
+    let preprocessed = preprocess::preprocess(&values);
+    if let Some(solution) = preprocessed.trivial() {
+        return solution.value as f64;
+    }
+    let values = preprocessed.values();
+    let epsilon = epsilon.into();
+    let value = if exact_is_cheaper(values, epsilon) {
+        meet_in_the_middle::exact_partition(values)
+            .expect("few enough items")
+            .sum as u64
+    } else {
+        let heuristic = karmarkar_karp::karmarkar_karp(values).sum as u64;
+        // Reduced weights never exceed the original ones.
+        let reduced = values.iter().map(|&x| x as u16).collect::<Vec<_>>();
+        ApproximateSumset::new::<T>(&reduced, epsilon)
+            .partition_value()
+            .max(heuristic)
+    };
+    (value * preprocessed.gcd()) as f64
+}
+
+// Whether meet in the middle lists no more half-sums than an approximation may hold sums.
+fn exact_is_cheaper(values: &[u64], epsilon: Epsilon) -> bool {
+    let n = values.len();
+    let sigma = values.iter().map(|&x| x as u128).sum::<u128>();
+    let approximate_sums = sigma.min(n as u128 * epsilon.inverse() as u128);
+    n <= meet_in_the_middle::MAX_ITEMS && 1u128 << (n / 2) <= approximate_sums
+}
+
+/// Like [`approximate_partition`], but every `(index, bin)` pin forces that item to a side, and
+/// the split itself is returned. The side not exceeding half of the total may be either one, so
+/// [`approximate_pinned_split`] runs with the pins as given and with their sides swapped, and
+/// the split whose smaller side is larger is kept. Either way `first` holds the items pinned to
+/// [`Bin::First`].
+pub fn approximate_pinned_partition<T: Convoluter>(
+    input: &[u16],
+    epsilon: impl Into<Epsilon>,
+    pins: &[(usize, Bin)],
+) -> Result<Split, SplitError> {
+    let epsilon = epsilon.into();
+    let half = Ratio::new(1, 2);
+    let split = approximate_pinned_split::<T>(input, half, epsilon, Side::Below, pins)?;
+    // Without pins both runs are the same.
+    if pins.is_empty() {
+        return Ok(split);
+    }
+    let swapped = pins
+        .iter()
+        .map(|&(index, bin)| match bin {
+            Bin::First => (index, Bin::Second),
+            Bin::Second => (index, Bin::First),
+        })
+        .collect::<Vec<_>>();
+    let other = approximate_pinned_split::<T>(input, half, epsilon, Side::Below, &swapped)?;
+    let smaller = |split: &Split| split.first_sum.min(split.second_sum);
+    if smaller(&other) <= smaller(&split) {
+        return Ok(split);
+    }
+    Ok(Split {
+        first: other.second,
+        second: other.first,
+        first_sum: other.second_sum,
+        second_sum: other.first_sum,
+        target: other.first_sum + other.second_sum - other.target,
+        error: other.error,
+    })
 }
 
 #[cfg(test)]
 mod tests {
-    use crate::{helpers::dynamic_programing_partition, Convoluter, FFT, NTT};
+    use crate::{
+        helpers::dynamic_programing_partition,
+        split::{Bin, SplitError},
+        Convoluter, FFT, NTT,
+    };
 
     fn validate_partition_approximation<T: Convoluter>(input: &[u16], epsilon: f64) {
         let approximation = super::approximate_partition::<T>(input, epsilon);
         let t: u64 = input.iter().copied().map(u64::from).sum::<u64>() / 2;
         let opt =
-            dynamic_programing_partition(&input.iter().copied().map(u64::from).collect::<Vec<_>>());
+            dynamic_programing_partition(&input.iter().copied().map(u64::from).collect::<Vec<_>>())
+                .unwrap();
         assert!(
             (opt as f64 - approximation) <= epsilon * t as f64,
             "{}, {}, {}",
@@ -71,6 +157,58 @@ mod tests {
         validate_partition_approximation::<NTT>(&input, 0.01);
     }
 
+    fn verify_pinned_partition<T: Convoluter>(input: &[u16], pins: &[(usize, Bin)]) {
+        let split = super::approximate_pinned_partition::<T>(input, 0.01, pins).unwrap();
+        for &(index, bin) in pins {
+            let side = if bin == Bin::First {
+                &split.first
+            } else {
+                &split.second
+            };
+            assert!(side.contains(&index));
+        }
+        // Every assignment of the free items, by brute force.
+        let free = (0..input.len())
+            .filter(|i| pins.iter().all(|pin| pin.0 != *i))
+            .collect::<Vec<_>>();
+        let sigma = input.iter().map(|&x| x as u64).sum::<u64>();
+        let pinned = |bin| {
+            pins.iter()
+                .filter(|pin| pin.1 == bin)
+                .map(|pin| input[pin.0] as u64)
+                .sum::<u64>()
+        };
+        let opt = (0..1u32 << free.len())
+            .map(|mask| {
+                let first = pinned(Bin::First)
+                    + (0..free.len())
+                        .filter(|j| mask >> j & 1 == 1)
+                        .map(|j| input[free[j]] as u64)
+                        .sum::<u64>();
+                first.min(sigma - first)
+            })
+            .max()
+            .unwrap();
+        let value = split.first_sum.min(split.second_sum);
+        assert!(value <= opt);
+        assert!(
+            (opt - value) as f64 <= 0.01 * (sigma / 2) as f64,
+            "{opt} {split:?}"
+        );
+    }
+
+    #[test]
+    fn test_pinned_partition() {
+        let input = [1001, 2000, 3000, 4000, 5000, 6000, 7000, 1000, 1003, 5];
+        verify_pinned_partition::<FFT>(&input, &[]);
+        verify_pinned_partition::<FFT>(&input, &[(6, Bin::First), (5, Bin::First)]);
+        verify_pinned_partition::<NTT>(&input, &[(6, Bin::Second), (0, Bin::First)]);
+        assert_eq!(
+            super::approximate_pinned_partition::<FFT>(&input, 0.01, &[(10, Bin::First)]),
+            Err(SplitError::NoSuchItem { index: 10 })
+        );
+    }
+
     #[test]
     fn test_partition_empty() {
         let input = [].to_vec();
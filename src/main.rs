@@ -8,10 +8,18 @@ use std::{
 };
 
 use partition::fft::FFT;
+use partition::helpers::dynamic_programing_partition;
 
 use clap::{Args, Parser, Subcommand, ValueEnum};
-use partition::helpers::{dynamic_programing_partition, naive_sumset};
-use partition::NTT;
+use partition::approximation::Side;
+use partition::karmarkar_karp::{complete_karmarkar_karp, karmarkar_karp, SearchLimits};
+use partition::preprocess::Preprocessing;
+use partition::solver::{
+    ApproximateSolver, DispatchSolver, GensLevnerSolver, KarmarkarKarpSolver, KellererSolver,
+    NaiveSolver, PartitionSolver,
+};
+use partition::split::{approximate_labeled_split, Bin, LabeledItem, LabeledSplit, Ratio, Split};
+use partition::{Epsilon, NTT};
 
 #[derive(Parser)]
 #[command(version, about)]
@@ -28,19 +36,55 @@ struct Cli {
 #[derive(Subcommand)]
 
 enum Comands {
-    /// Approximates the partition of a set of integers using a specified approximation parameter.
+    /// Approximates the partition of a set of integers using a specified approximation parameter,
+    /// printing a table assigning every item to a side.
     Partition {
-        /// The approximation parameter.
-        epsilon: f64,
+        /// The approximation parameter, in (0, 1].
+        #[arg(value_parser = parse_epsilon)]
+        epsilon: Epsilon,
         /// Specifies the input file path. If not provided, input is read from the standard input.
         /// The input file or stdin should contain a list of u16 integers separated by whitespace.
+        /// Lines of the form `label<TAB>weight`, where the label is not itself a number, give an
+        /// item a label, other items are labeled by their position.
+        #[arg(short, long, value_name = "FILE")]
+        input: Option<PathBuf>,
+        /// Splits the input so that the first side sums to this fraction of the total, printing
+        /// a table assigning every item to a side.
+        #[arg(long)]
+        ratio: Option<Ratio>,
+        /// Which side of the ratio target the first side may land on.
+        #[arg(long, value_enum, default_value_t, requires = "ratio")]
+        side: SplitSide,
+        /// Forces the item at INDEX to a side, given as `INDEX=first` or `INDEX=second`. May be
+        /// repeated.
+        #[arg(long, value_name = "INDEX=SIDE", value_parser = parse_pin)]
+        pin: Vec<(usize, Bin)>,
+    },
+    /// Partitions a set of integers with the Karmarkar–Karp differencing heuristic, printing a
+    /// table assigning every item to a side, with the smaller side first.
+    KarmarkarKarp {
+        /// Specifies the input file path. If not provided, input is read from the standard input.
         #[arg(short, long, value_name = "FILE")]
         input: Option<PathBuf>,
+        /// Keeps searching with the complete Karmarkar–Karp algorithm until the partition is
+        /// optimal or a limit is reached.
+        #[arg(long)]
+        complete: bool,
+        /// The largest number of search nodes of the complete search.
+        #[arg(long, requires = "complete")]
+        node_limit: Option<u64>,
+        /// The longest time in milliseconds of the complete search.
+        #[arg(long, requires = "complete")]
+        time_limit: Option<u64>,
     },
-    /// Runs a benchmark with specified options.
+    /// Runs a benchmark of the approximate partition solver with specified options.
     /// The benchmark is run on a randomly generated data for each combination of epsilon and input length specified.
     Benchmark(BenchmarkOptions),
-    /// Runs a benchmark using the naive approach
+    /// Compares the approximation of the partition value with the Gens–Levner and Kellerer et al.
+    /// FPTAS baselines and the Karmarkar–Karp heuristic, reporting the average value found, the
+    /// average certified bounds and the average time of each.
+    CompareBenchmark(BenchmarkOptions),
+    /// Runs a benchmark using the naive approach, for at most 25 items
     NaiveBenchmark(NaiveBenchmarkOptions),
     /// Runs a benchmark using the dynamic programming approach
     DynamicProgramingBenchmark(DynamicProgramingBenchmarkOptions),
@@ -50,6 +94,7 @@ enum Comands {
 /// Options for benchmarking, including ranges for epsilon and input lengths, and the convoluter type.
 struct BenchmarkOptions {
     /// The start of the inverse of epsilon values to use for the benchmark. Eg. start=2, end=6, step=2 will use epsilon values 1/2, 1/4, 1/6.
+    #[arg(value_parser = clap::value_parser!(u64).range(1..))]
     epsilon_inverse_range_start: u64,
     /// The end of the range of the inverse of epsilon values.
     epsilon_inverse_range_end: u64,
@@ -175,6 +220,25 @@ pub enum Convoluter {
     NTT,
 }
 
+#[derive(Debug, Clone, Copy, ValueEnum, Default)]
+/// Enum representing where a ratio split may land relative to its target.
+pub enum SplitSide {
+    #[default]
+    /// The first side may not exceed the target
+    Below,
+    /// The first side may be on either side of the target
+    Nearest,
+}
+
+impl From<SplitSide> for Side {
+    fn from(side: SplitSide) -> Self {
+        match side {
+            SplitSide::Below => Side::Below,
+            SplitSide::Nearest => Side::Nearest,
+        }
+    }
+}
+
 impl Display for Convoluter {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         match self {
@@ -187,8 +251,27 @@ impl Display for Convoluter {
 fn main() -> io::Result<()> {
     let cli = Cli::parse();
     let output = match &cli.subcommand {
-        Comands::Partition { epsilon, input } => partition_subcommand(input, epsilon),
+        Comands::Partition {
+            epsilon,
+            input,
+            ratio: Some(ratio),
+            side,
+            pin,
+        } => ratio_split_subcommand(input, epsilon, *ratio, *side, pin),
+        Comands::Partition {
+            epsilon,
+            input,
+            pin,
+            ..
+        } => partition_subcommand(input, epsilon, pin),
+        Comands::KarmarkarKarp {
+            input,
+            complete,
+            node_limit,
+            time_limit,
+        } => karmarkar_karp_subcommand(input, *complete, *node_limit, *time_limit),
         Comands::Benchmark(options) => benchmark_subcommand(options),
+        Comands::CompareBenchmark(options) => compare_benchmark_subcommand(options),
         Comands::NaiveBenchmark(options) => naive_benchmark_subcommand(options),
         Comands::DynamicProgramingBenchmark(options) => {
             dynamic_programing_benchmark_subcommand(options)
@@ -198,21 +281,144 @@ fn main() -> io::Result<()> {
     Ok(())
 }
 
-fn partition_subcommand(input: &Option<PathBuf>, epsilon: &f64) -> Result<String, io::Error> {
-    Ok(
-        partition::approximate_partition::<FFT>(&parse_input_as_vec(buf_reader(input)?)?, *epsilon)
-            .to_string(),
+fn partition_subcommand(
+    input: &Option<PathBuf>,
+    epsilon: &Epsilon,
+    pins: &[(usize, Bin)],
+) -> Result<String, io::Error> {
+    let items = parse_labeled_input(buf_reader(input)?)?;
+    let weights = items.iter().map(|item| item.weight).collect::<Vec<_>>();
+    if !pins.is_empty() {
+        let split = partition::approximate_pinned_partition::<FFT>(&weights, *epsilon, pins)
+            .map_err(|error| io::Error::new(io::ErrorKind::InvalidInput, error))?;
+        return Ok(split_table(&items, &LabeledSplit::new(&items, split)));
+    }
+    // Without pins the solver behind `approximate_partition` runs, with its preprocessing, exact
+    // dispatch and Karmarkar–Karp floor.
+    let input = weights.iter().copied().map(u64::from).collect::<Vec<_>>();
+    let solver = Preprocessing {
+        solver: DispatchSolver::<FFT>::new(*epsilon),
+    };
+    let result = solver
+        .solve(&input)
+        .map_err(|error| io::Error::new(io::ErrorKind::InvalidInput, error))?;
+    let mut first = result
+        .witness
+        .expect("every solver dispatched to on u16 weights tells the items");
+    first.sort_unstable();
+    let target = input.iter().sum::<u64>() / 2;
+    let split = Split::from_first(&weights, first, target, result.upper_bound - result.value);
+    Ok(split_table(&items, &LabeledSplit::new(&items, split)))
+}
+
+fn ratio_split_subcommand(
+    input: &Option<PathBuf>,
+    epsilon: &Epsilon,
+    ratio: Ratio,
+    side: SplitSide,
+    pins: &[(usize, Bin)],
+) -> Result<String, io::Error> {
+    let items = parse_labeled_input(buf_reader(input)?)?;
+    let split = approximate_labeled_split::<FFT, _>(&items, ratio, *epsilon, side.into(), pins)
+        .map_err(|error| io::Error::new(io::ErrorKind::InvalidInput, error))?;
+    Ok(split_table(&items, &split))
+}
+
+fn split_table(items: &[LabeledItem<String>], split: &LabeledSplit<String>) -> String {
+    use std::fmt::Write;
+    let width = items
+        .iter()
+        .map(|item| item.label.len())
+        .chain(["label".len()])
+        .max()
+        .unwrap();
+    let mut table = format!("{:<6}  {:<width$}  weight\n", "side", "label");
+    for (name, side) in [("first", &split.first), ("second", &split.second)] {
+        for item in side {
+            writeln!(
+                table,
+                "{:<6}  {:<width$}  {}",
+                name, item.label, item.weight
+            )
+            .unwrap();
+        }
+    }
+    write!(
+        table,
+        "first sum {}, second sum {}, target {}, error bound {}",
+        split.split.first_sum, split.split.second_sum, split.split.target, split.split.error
     )
+    .unwrap();
+    table
+}
+
+fn parse_epsilon(epsilon: &str) -> Result<Epsilon, String> {
+    let value = epsilon
+        .trim()
+        .parse()
+        .map_err(|_| format!("invalid epsilon '{epsilon}'"))?;
+    Epsilon::try_at_most(value).ok_or_else(|| format!("epsilon must be in (0, 1], got '{epsilon}'"))
+}
+
+fn parse_pin(pin: &str) -> Result<(usize, Bin), String> {
+    let (index, bin) = pin
+        .split_once('=')
+        .ok_or_else(|| format!("expected INDEX=SIDE, got '{pin}'"))?;
+    let index = index
+        .trim()
+        .parse()
+        .map_err(|_| format!("invalid item index '{index}'"))?;
+    let bin = match bin.trim() {
+        "first" => Bin::First,
+        "second" => Bin::Second,
+        _ => return Err(format!("side must be 'first' or 'second', got '{bin}'")),
+    };
+    Ok((index, bin))
+}
+
+fn karmarkar_karp_subcommand(
+    input: &Option<PathBuf>,
+    complete: bool,
+    node_limit: Option<u64>,
+    time_limit: Option<u64>,
+) -> Result<String, io::Error> {
+    let items = parse_labeled_input(buf_reader(input)?)?;
+    let weights = items.iter().map(|item| item.weight).collect::<Vec<_>>();
+    let input = weights.iter().copied().map(u64::from).collect::<Vec<_>>();
+    let (witness, optimal) = if complete {
+        let limits = SearchLimits {
+            nodes: node_limit,
+            time: time_limit.map(std::time::Duration::from_millis),
+        };
+        let result = complete_karmarkar_karp(&input, limits);
+        if !result.optimal {
+            eprintln!("Warning: Search limit reached, the partition may not be optimal");
+        }
+        (result.witness, result.optimal)
+    } else {
+        (karmarkar_karp(&input), false)
+    };
+    let mut first = witness.items;
+    first.sort_unstable();
+    let target = input.iter().sum::<u64>() / 2;
+    // Without a proof of optimality the distance to the target is all that is known.
+    let error = if optimal {
+        0
+    } else {
+        (witness.sum as u64).abs_diff(target)
+    };
+    let split = Split::from_first(&weights, first, target, error);
+    Ok(split_table(&items, &LabeledSplit::new(&items, split)))
 }
 
 fn benchmark_subcommand(options: &BenchmarkOptions) -> Result<String, io::Error> {
     use std::fmt::Write;
     let mut results = vec![];
     for epsilon in options.epsilon_range() {
-        let epsilon = 1f64 / epsilon as f64;
+        let epsilon = Epsilon::from_inverse(epsilon);
         for input_length in options.input_length_range() {
             let config = BenchmarkConfig {
-                epsilon: epsilon,
+                epsilon,
                 input_length,
                 convoluter: options.convoluter,
                 repetitions: options.repetitions,
@@ -229,6 +435,29 @@ fn benchmark_subcommand(options: &BenchmarkOptions) -> Result<String, io::Error>
     Ok(output)
 }
 
+fn compare_benchmark_subcommand(options: &BenchmarkOptions) -> Result<String, io::Error> {
+    use std::fmt::Write;
+    let mut results = vec![];
+    for epsilon in options.epsilon_range() {
+        let epsilon = Epsilon::from_inverse(epsilon);
+        for input_length in options.input_length_range() {
+            let config = CompareBenchmarkConfig {
+                epsilon,
+                input_length,
+                convoluter: options.convoluter,
+                repetitions: options.repetitions,
+            };
+            results.extend(config.benchmark_single()?);
+        }
+    }
+    let mut output = String::new();
+    writeln!(output, "{}", CompareBenchmarkResult::HEADER).unwrap();
+    for result in results {
+        writeln!(output, "{}", result.to_cs_row()).unwrap();
+    }
+    Ok(output)
+}
+
 fn naive_benchmark_subcommand(options: &NaiveBenchmarkOptions) -> Result<String, io::Error> {
     use std::fmt::Write;
     let mut results = vec![];
@@ -271,7 +500,7 @@ fn dynamic_programing_benchmark_subcommand(
 }
 
 struct BenchmarkConfig {
-    epsilon: f64,
+    epsilon: Epsilon,
     input_length: usize,
     convoluter: Convoluter,
     repetitions: usize,
@@ -292,6 +521,8 @@ impl BenchmarkConfig {
             let input = (0..self.input_length)
                 .map(|_| rand::random::<u16>())
                 .collect::<Vec<_>>();
+            // Only the sumset approximation is timed, not the heuristic `ApproximateSolver` adds
+            // for its value.
             let start = std::time::Instant::now();
             match self.convoluter {
                 Convoluter::FFT => partition::approximate_sumset::<FFT>(&input, self.epsilon),
@@ -304,7 +535,7 @@ impl BenchmarkConfig {
 }
 
 struct BenchmarkResult {
-    epsilon: f64,
+    epsilon: Epsilon,
     input_length: usize,
     convoluter: Convoluter,
     times: Vec<u128>,
@@ -318,7 +549,7 @@ impl BenchmarkResult {
     fn to_cs_row(&self) -> String {
         format!(
             "{}, {}, {}, {}",
-            self.epsilon,
+            self.epsilon.value(),
             self.input_length,
             self.convoluter,
             self.average_time()
@@ -326,6 +557,103 @@ impl BenchmarkResult {
     }
 }
 
+struct CompareBenchmarkConfig {
+    epsilon: Epsilon,
+    input_length: usize,
+    convoluter: Convoluter,
+    repetitions: usize,
+}
+
+impl CompareBenchmarkConfig {
+    fn solvers(&self) -> Vec<Box<dyn PartitionSolver>> {
+        let approximation: Box<dyn PartitionSolver> = match self.convoluter {
+            Convoluter::FFT => Box::new(ApproximateSolver::<FFT>::new(self.epsilon)),
+            Convoluter::NTT => Box::new(ApproximateSolver::<NTT>::new(self.epsilon)),
+        };
+        vec![
+            approximation,
+            Box::new(GensLevnerSolver {
+                epsilon: self.epsilon,
+            }),
+            Box::new(KellererSolver {
+                epsilon: self.epsilon,
+            }),
+            Box::new(KarmarkarKarpSolver),
+        ]
+    }
+    fn benchmark_single(self) -> Result<Vec<CompareBenchmarkResult>, io::Error> {
+        let solvers = self.solvers();
+        let mut values = vec![Vec::new(); solvers.len()];
+        let mut lower_bounds = vec![Vec::new(); solvers.len()];
+        let mut upper_bounds = vec![Vec::new(); solvers.len()];
+        let mut times = vec![Vec::new(); solvers.len()];
+        for _ in 0..self.repetitions {
+            let input = (0..self.input_length)
+                .map(|_| rand::random::<u16>() as u64)
+                .collect::<Vec<_>>();
+            for (i, solver) in solvers.iter().enumerate() {
+                let result = solver
+                    .solve(&input)
+                    .map_err(|error| io::Error::new(io::ErrorKind::InvalidInput, error))?;
+                values[i].push(result.value as f64);
+                lower_bounds[i].push(result.lower_bound as f64);
+                upper_bounds[i].push(result.upper_bound as f64);
+                times[i].push(result.elapsed.as_nanos());
+            }
+        }
+        Ok(solvers
+            .iter()
+            .zip(values.into_iter().zip(lower_bounds).zip(upper_bounds))
+            .zip(times)
+            .map(
+                |((solver, ((values, lower_bounds), upper_bounds)), times)| {
+                    CompareBenchmarkResult {
+                        epsilon: self.epsilon,
+                        input_length: self.input_length,
+                        algorithm: solver.info().name,
+                        values,
+                        lower_bounds,
+                        upper_bounds,
+                        times,
+                    }
+                },
+            )
+            .collect())
+    }
+}
+
+struct CompareBenchmarkResult {
+    epsilon: Epsilon,
+    input_length: usize,
+    algorithm: &'static str,
+    values: Vec<f64>,
+    lower_bounds: Vec<f64>,
+    upper_bounds: Vec<f64>,
+    times: Vec<u128>,
+}
+
+impl CompareBenchmarkResult {
+    fn average(values: &[f64]) -> f64 {
+        values.iter().sum::<f64>() / values.len() as f64
+    }
+    fn average_time(&self) -> f64 {
+        self.times.iter().map(|x| *x as f64).sum::<f64>() / self.times.len() as f64
+    }
+    const HEADER: &'static str = "epsilon, input_length, algorithm, average_value, average_lower_bound, average_upper_bound, average_time";
+    fn to_cs_row(&self) -> String {
+        format!(
+            "{}, {}, {}, {}, {}, {}, {}",
+            self.epsilon.value(),
+            self.input_length,
+            self.algorithm,
+            Self::average(&self.values),
+            Self::average(&self.lower_bounds),
+            Self::average(&self.upper_bounds),
+            self.average_time()
+        )
+    }
+}
+
 struct NaiveBenchmarkConfig {
     input_length: usize,
     repetitions: usize,
@@ -342,11 +670,9 @@ impl NaiveBenchmarkConfig {
         let mut times = Vec::new();
         for _ in 0..self.repetitions {
             let input = (0..self.input_length)
-                .map(|_| rand::random::<u16>())
+                .map(|_| rand::random::<u16>() as u64)
                 .collect::<Vec<_>>();
-            let start = std::time::Instant::now();
-            naive_sumset(&input.iter().copied().map(u64::from).collect::<Vec<_>>());
-            times.push(start.elapsed().as_nanos());
+            times.push(solve_time(&NaiveSolver, &input)?);
         }
         Ok(self.result(times))
     }
@@ -387,8 +713,14 @@ impl DynamicProgramingBenchmarkConfig {
             let input = (0..self.input_length)
                 .map(|_| rand::random::<u64>() % self.max_value + 1)
                 .collect::<Vec<_>>();
+            // The value alone, as `DynamicProgramingSolver` also recovers the items.
             let start = std::time::Instant::now();
-            dynamic_programing_partition(&input);
+            dynamic_programing_partition(&input).ok_or_else(|| {
+                io::Error::new(
+                    io::ErrorKind::InvalidInput,
+                    "the table does not fit in memory",
+                )
+            })?;
             times.push(start.elapsed().as_nanos());
         }
         Ok(self.result(times))
@@ -416,6 +748,13 @@ impl DynamicProgramingBenchmarkResult {
     }
 }
 
+fn solve_time(solver: &dyn PartitionSolver, input: &[u64]) -> Result<u128, io::Error> {
+    let result = solver
+        .solve(input)
+        .map_err(|error| io::Error::new(io::ErrorKind::InvalidInput, error))?;
+    Ok(result.elapsed.as_nanos())
+}
+
 fn buf_reader(input: &Option<PathBuf>) -> io::Result<Box<dyn BufRead>> {
     match input {
         Some(path) => {
@@ -426,18 +765,37 @@ fn buf_reader(input: &Option<PathBuf>) -> io::Result<Box<dyn BufRead>> {
     }
 }
 
-fn parse_input_as_vec<R: BufRead>(reader: R) -> io::Result<Vec<u16>> {
-    let mut values = Vec::new();
+fn parse_labeled_input<R: BufRead>(reader: R) -> io::Result<Vec<LabeledItem<String>>> {
+    let mut items = Vec::new();
     for line in reader.lines() {
         let line = line?;
+        // A tab only separates a label when it follows something other than weights, so
+        // tab-separated numbers still read as plain weights.
+        if let Some((label, weight)) = line.split_once('\t').filter(|(label, _)| {
+            label
+                .split_whitespace()
+                .any(|word| u16::from_str(word).is_err())
+        }) {
+            match u16::from_str(weight.trim()) {
+                Ok(weight) => items.push(LabeledItem {
+                    label: label.trim().to_string(),
+                    weight,
+                }),
+                Err(_) => eprintln!("Warning: Skipping invalid line '{}'", line),
+            }
+            continue;
+        }
         for word in line.split_whitespace() {
             match u16::from_str(word) {
-                Ok(value) => values.push(value),
+                Ok(weight) => items.push(LabeledItem {
+                    label: items.len().to_string(),
+                    weight,
+                }),
                 Err(_) => eprintln!("Warning: Skipping invalid value '{}'", word),
             }
         }
     }
-    Ok(values)
+    Ok(items)
 }
 
 fn write_result(output: &Option<PathBuf>, result: String) -> Result<(), io::Error> {
@@ -448,3 +806,29 @@ fn write_result(output: &Option<PathBuf>, result: String) -> Result<(), io::Erro
     writeln!(writer, "{result}")?;
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_labeled_input() {
+        let weights = |input: &str| {
+            parse_labeled_input(input.as_bytes())
+                .expect("reading from a slice cannot fail")
+                .into_iter()
+                .map(|item| item.weight)
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(weights("1\t2\t3\n"), vec![1, 2, 3]);
+        assert_eq!(weights("1 2\t3\n4\n"), vec![1, 2, 3, 4]);
+        assert_eq!(weights("1 2 3\n"), vec![1, 2, 3]);
+
+        let items = parse_labeled_input("apple\t5\n6\t7\npear\t 2 \n".as_bytes()).unwrap();
+        let labeled = items
+            .iter()
+            .map(|item| (item.label.as_str(), item.weight))
+            .collect::<Vec<_>>();
+        assert_eq!(labeled, vec![("apple", 5), ("1", 6), ("2", 7), ("pear", 2)]);
+    }
+}
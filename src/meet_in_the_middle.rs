@@ -0,0 +1,256 @@
+use std::{cmp::Reverse, collections::BinaryHeap};
+
+use crate::solver::SolverError;
+
+/// A subset of the input, given by item indices, with its sum.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SubsetWitness {
+    pub items: Vec<usize>,
+    pub sum: u128,
+}
+
+/// The most items [`exact_subset_sum`] accepts, with two lists of at most `2^20` sums.
+///
+/// A listed sum with its mask takes 32 bytes, and building the second list holds the first one
+/// next to the last merge, which is twice as large. At 40 items this peaks at
+/// `3 * 2^20 * 32` bytes, about 100 MB, where 48 items would take about 1.5 GB. For more items,
+/// [`schroeppel_shamir_subset_sum`] keeps its lists small.
+pub const MAX_ITEMS: usize = 40;
+
+/// The most items [`schroeppel_shamir_subset_sum`] accepts, with four lists of at most `2^16`
+/// sums.
+pub const SCHROEPPEL_SHAMIR_MAX_ITEMS: usize = 64;
+
+/// Finds a subset with the largest sum not exceeding `t`, exactly.
+///
+/// This is the Horowitz–Sahni meet-in-the-middle: the subset sums of both halves are listed in
+/// sorted order, and a two-pointer sweep finds the best pair. It takes `O(2^(n/2))` time and
+/// memory regardless of the weights, so it suits few items of huge size, where convolutions over
+/// the value range do not. Sums are kept in `u128`, so no combination of `u64` weights overflows.
+/// Fails with more than [`MAX_ITEMS`] items.
+pub fn exact_subset_sum(input: &[u64], t: u128) -> Result<SubsetWitness, SolverError> {
+    check_items(input, MAX_ITEMS)?;
+    let (left, right) = input.split_at(input.len() / 2);
+    let left = sorted_sums(left);
+    let right = sorted_sums(right);
+    let mut best = (0, 0, 0);
+    let mut j = right.len();
+    for &(a, left_mask) in left.iter().take_while(|&&(a, _)| a <= t) {
+        while right[j - 1].0 > t - a {
+            j -= 1;
+        }
+        let (b, right_mask) = right[j - 1];
+        if a + b > best.0 {
+            best = (a + b, left_mask, right_mask);
+        }
+    }
+    let half = input.len() / 2;
+    let items = (0..half)
+        .filter(|&i| best.1 >> i & 1 == 1)
+        .chain((half..input.len()).filter(|&i| best.2 >> (i - half) & 1 == 1))
+        .collect();
+    Ok(SubsetWitness { items, sum: best.0 })
+}
+
+/// Finds a subset with the largest sum not exceeding half of the total, exactly. See
+/// [`exact_subset_sum`].
+pub fn exact_partition(input: &[u64]) -> Result<SubsetWitness, SolverError> {
+    let sigma: u128 = input.iter().map(|&x| x as u128).sum();
+    exact_subset_sum(input, sigma / 2)
+}
+
+/// Like [`exact_subset_sum`], but in `O(2^(n/4))` memory instead of `O(2^(n/2))`.
+///
+/// This is the Schroeppel–Shamir variant: the items are split into quarters `A, B, C, D`, whose
+/// sorted subset sums are small. The sums of `A + B` are then generated in increasing order and
+/// those of `C + D` in decreasing order, each by a heap holding one candidate per sum of the
+/// first quarter, and the same two-pointer sweep finds the best pair. It still takes
+/// `O(2^(n/2) * n)` time. Fails with more than [`SCHROEPPEL_SHAMIR_MAX_ITEMS`] items.
+pub fn schroeppel_shamir_subset_sum(input: &[u64], t: u128) -> Result<SubsetWitness, SolverError> {
+    check_items(input, SCHROEPPEL_SHAMIR_MAX_ITEMS)?;
+    let (left, right) = input.split_at(input.len() / 2);
+    let quarters = [
+        left.split_at(left.len() / 2),
+        right.split_at(right.len() / 2),
+    ];
+    let offsets = [
+        0,
+        quarters[0].0.len(),
+        left.len(),
+        left.len() + quarters[1].0.len(),
+    ];
+    let [(a, b), (c, d)] = quarters.map(|(x, y)| (sorted_sums(x), sorted_sums(y)));
+
+    // The pairs `(i, j)` of `a[i] + b[j]`, smallest first.
+    let mut increasing = (0..a.len())
+        .map(|i| Reverse((a[i].0 + b[0].0, i, 0)))
+        .collect::<BinaryHeap<_>>();
+    // The pairs `(k, l)` of `c[k] + d[l]`, largest first.
+    let mut decreasing = (0..c.len())
+        .map(|k| (c[k].0 + d[d.len() - 1].0, k, d.len() - 1))
+        .collect::<BinaryHeap<_>>();
+    // The best sum with its index into each list.
+    let mut best: Option<(u128, [usize; 4])> = None;
+    while let Some(Reverse((low, i, j))) = increasing.pop() {
+        if low > t {
+            break;
+        }
+        if j + 1 < b.len() {
+            increasing.push(Reverse((a[i].0 + b[j + 1].0, i, j + 1)));
+        }
+        // The sums of `C + D` only have to shrink as the sums of `A + B` grow.
+        while let Some(&(high, k, l)) = decreasing.peek() {
+            if low + high <= t {
+                break;
+            }
+            decreasing.pop();
+            if l > 0 {
+                decreasing.push((c[k].0 + d[l - 1].0, k, l - 1));
+            }
+        }
+        let Some(&(high, k, l)) = decreasing.peek() else {
+            break;
+        };
+        if best.map_or(true, |(sum, ..)| low + high > sum) {
+            best = Some((low + high, [i, j, k, l]));
+        }
+    }
+    // The empty set lies in both halves, so the smallest pair always fits a non-negative target.
+    let (sum, [i, j, k, l]) = best.expect("the empty subset never exceeds the target");
+    let masks = [a[i].1, b[j].1, c[k].1, d[l].1];
+    let items = offsets
+        .into_iter()
+        .zip(masks)
+        .flat_map(|(offset, mask)| {
+            (0..u32::BITS as usize)
+                .filter(move |&i| mask >> i & 1 == 1)
+                .map(move |i| offset + i)
+        })
+        .collect();
+    Ok(SubsetWitness { items, sum })
+}
+
+fn check_items(input: &[u64], max: usize) -> Result<(), SolverError> {
+    if input.len() > max {
+        return Err(SolverError::TooManyItems {
+            items: input.len(),
+            max,
+        });
+    }
+    Ok(())
+}
+
+// Lists every subset sum with its item mask in increasing order, adding one item at a time by
+// merging the list with its shifted copy. The masks are `u32`, as no list covers more than a
+// half of [`MAX_ITEMS`] or a quarter of [`SCHROEPPEL_SHAMIR_MAX_ITEMS`] items.
+fn sorted_sums(input: &[u64]) -> Vec<(u128, u32)> {
+    assert!(input.len() <= u32::BITS as usize);
+    let mut sums = vec![(0u128, 0u32)];
+    for (i, &x) in input.iter().enumerate() {
+        let shifted = sums
+            .iter()
+            .map(|&(sum, mask)| (sum + x as u128, mask | 1 << i))
+            .collect::<Vec<_>>();
+        let mut merged = Vec::with_capacity(sums.len() * 2);
+        let (mut a, mut b) = (sums.into_iter().peekable(), shifted.into_iter().peekable());
+        while let (Some(x), Some(y)) = (a.peek(), b.peek()) {
+            merged.push(if x.0 <= y.0 { a.next() } else { b.next() }.unwrap());
+        }
+        merged.extend(a.chain(b));
+        sums = merged;
+    }
+    sums
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::helpers::{dynamic_programing_partition, naive_sumset};
+
+    use super::*;
+
+    fn verify_subset(input: &[u64], witness: &SubsetWitness) {
+        let sum: u128 = witness.items.iter().map(|&i| input[i] as u128).sum();
+        assert_eq!(sum, witness.sum);
+    }
+
+    #[test]
+    fn test_exact_subset_sum() {
+        let input = [
+            1001, 2000, 3000, 4000, 5000, 6000, 7000, 1000, 1001, 1002, 1003, 5,
+        ];
+        let sums = naive_sumset(&input);
+        for t in [0, 4, 5, 1006, 9999, 20000, 31011, 40000] {
+            let witness = exact_subset_sum(&input, t as u128).unwrap();
+            verify_subset(&input, &witness);
+            let best = sums.iter().copied().filter(|&s| s <= t).max().unwrap_or(0);
+            assert_eq!(witness.sum, best as u128);
+        }
+    }
+
+    #[test]
+    fn test_exact_partition() {
+        let input = [
+            1001, 2000, 3000, 4000, 5000, 6000, 7000, 8000, 9000, 1000, 1001, 1002, 5,
+        ];
+        let witness = exact_partition(&input).unwrap();
+        verify_subset(&input, &witness);
+        assert_eq!(
+            witness.sum,
+            dynamic_programing_partition(&input).unwrap() as u128
+        );
+        assert_eq!(exact_partition(&[]).unwrap().sum, 0);
+        assert_eq!(exact_partition(&[7]).unwrap().sum, 0);
+    }
+
+    #[test]
+    fn test_schroeppel_shamir_subset_sum() {
+        let input = [
+            1001, 2000, 3000, 4000, 5000, 6000, 7000, 1000, 1001, 1002, 1003, 5, 77,
+        ];
+        let sums = naive_sumset(&input);
+        for t in [0, 4, 5, 1006, 9999, 20000, 31011, 40000] {
+            let witness = schroeppel_shamir_subset_sum(&input, t as u128).unwrap();
+            verify_subset(&input, &witness);
+            let best = sums.iter().copied().filter(|&s| s <= t).max().unwrap_or(0);
+            assert_eq!(witness.sum, best as u128);
+        }
+        for input in [&[][..], &[7], &[3, 4], &[5, 6, 7]] {
+            let t = input.iter().sum::<u64>() as u128 / 2;
+            let witness = schroeppel_shamir_subset_sum(input, t).unwrap();
+            verify_subset(input, &witness);
+            assert_eq!(witness.sum, exact_subset_sum(input, t).unwrap().sum);
+        }
+        let input = (0..40)
+            .map(|i| (1 << 60) + i * i * 12345)
+            .collect::<Vec<u64>>();
+        let sigma: u128 = input.iter().map(|&x| x as u128).sum();
+        let witness = schroeppel_shamir_subset_sum(&input, sigma / 2).unwrap();
+        verify_subset(&input, &witness);
+        assert_eq!(witness.sum, exact_partition(&input).unwrap().sum);
+    }
+
+    #[test]
+    fn test_item_limits() {
+        assert_eq!(
+            exact_partition(&[1; 41]),
+            Err(SolverError::TooManyItems { items: 41, max: 40 })
+        );
+        assert_eq!(
+            schroeppel_shamir_subset_sum(&[1; 65], 10),
+            Err(SolverError::TooManyItems { items: 65, max: 64 })
+        );
+    }
+
+    #[test]
+    fn test_exact_partition_huge_weights() {
+        let input = (0..40)
+            .map(|i| (1 << 60) + i * i * 12345)
+            .collect::<Vec<u64>>();
+        let witness = exact_partition(&input).unwrap();
+        verify_subset(&input, &witness);
+        let sigma: u128 = input.iter().map(|&x| x as u128).sum();
+        assert_eq!(witness.items.len(), 20);
+        assert!(witness.sum <= sigma / 2);
+        assert!(sigma / 2 - witness.sum < 12345);
+    }
+}
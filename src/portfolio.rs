@@ -0,0 +1,219 @@
+use std::{
+    marker::PhantomData,
+    sync::{mpsc, Arc},
+    thread,
+    time::{Duration, Instant},
+};
+
+use crate::{
+    approximation::{Epsilon, PreparedInput},
+    context::{CancellationToken, Context, Interrupted},
+    dynamic_programing::bitset_subset_sum_with_context,
+    fft::Convoluter,
+    karmarkar_karp::karmarkar_karp,
+    solver::{
+        merge_bounds, Guarantee, PartitionSolver, Solution, SolverError, SolverInfo, SolverKind,
+    },
+};
+
+/// Races several partition methods and returns the best certified result once the deadline
+/// expires, every method has finished, or the bounds meet.
+///
+/// Karmarkar–Karp runs first and seeds the result. Then the sumset approximation runs for each of
+/// `epsilons` in turn, coarse to fine, next to the exact dynamic program when half of the total is
+/// at most `exact_limit`. The methods run on detached threads, so the race ends on time even
+/// while one of them is inside a single large convolution. When it ends, the remaining methods
+/// are cancelled through the shared context and stop at their next check.
+pub struct Portfolio<T: Convoluter> {
+    pub deadline: Duration,
+    pub epsilons: Vec<Epsilon>,
+    pub exact_limit: u64,
+    _phantom: PhantomData<T>,
+}
+
+impl<T: Convoluter> Portfolio<T> {
+    pub fn new(deadline: Duration) -> Self {
+        Self {
+            deadline,
+            epsilons: [10, 100, 1000].map(Epsilon::from_inverse).to_vec(),
+            exact_limit: 1 << 26,
+            _phantom: PhantomData,
+        }
+    }
+}
+
+impl<T: Convoluter + 'static> PartitionSolver for Portfolio<T> {
+    fn info(&self) -> SolverInfo {
+        SolverInfo {
+            name: "portfolio",
+            kind: SolverKind::Approximate,
+            guarantee: Guarantee::None,
+        }
+    }
+    fn partition(&self, input: &[u64]) -> Result<Solution, SolverError> {
+        let start = Instant::now();
+        let sigma = input
+            .iter()
+            .try_fold(0u64, |sum, &x| sum.checked_add(x))
+            .ok_or(SolverError::SumOverflow)?;
+        let t = sigma / 2;
+        let heuristic = karmarkar_karp(input);
+        let mut best = Solution {
+            value: heuristic.sum as u64,
+            witness: Some(heuristic.items),
+            lower_bound: heuristic.sum as u64,
+            upper_bound: t,
+        };
+        let context = Arc::new(Context::new().with_deadline(start + self.deadline));
+        let _cancel = CancelOnDrop(context.cancellation().clone());
+        let (sender, receiver) = mpsc::channel();
+        let small = input
+            .iter()
+            .map(|&x| u16::try_from(x).ok())
+            .collect::<Option<Vec<_>>>();
+        if let Some(small) = small {
+            let (sender, context) = (sender.clone(), Arc::clone(&context));
+            let epsilons = self.epsilons.clone();
+            thread::spawn(move || {
+                let prepared = PreparedInput::new(&small);
+                for epsilon in epsilons {
+                    let Ok(bounds) = approximate_bounds::<T>(&prepared, epsilon, &context) else {
+                        return;
+                    };
+                    if sender.send(Found::Bounds(bounds)).is_err() {
+                        return;
+                    }
+                }
+            });
+        }
+        if t <= self.exact_limit {
+            let (sender, context) = (sender.clone(), Arc::clone(&context));
+            let input = input.to_vec();
+            thread::spawn(move || {
+                if let Ok(value) = bitset_subset_sum_with_context(&input, t, &context) {
+                    let _ = sender.send(Found::Optimum(value));
+                }
+            });
+        }
+        drop(sender);
+        while best.lower_bound < best.upper_bound {
+            let remaining = self.deadline.saturating_sub(start.elapsed());
+            let Ok(found) = receiver.recv_timeout(remaining) else {
+                break;
+            };
+            let bounds = match found {
+                Found::Bounds(bounds) => bounds,
+                Found::Optimum(value) => (value, value),
+            };
+            let Some(merged) = merge_bounds((best.lower_bound, best.upper_bound), bounds) else {
+                continue;
+            };
+            if let Found::Optimum(value) = found {
+                best.value = value;
+                best.witness = None;
+            }
+            (best.lower_bound, best.upper_bound) = merged;
+        }
+        debug_assert!(best.lower_bound <= best.upper_bound);
+        Ok(best)
+    }
+}
+
+// Cancels the workers when the race ends, however it ends, so none of them keeps running until
+// its own deadline check.
+struct CancelOnDrop(CancellationToken);
+
+impl Drop for CancelOnDrop {
+    fn drop(&mut self) {
+        self.0.cancel();
+    }
+}
+
+// What a worker reports: certified bounds without an achievable sum, or the optimum itself.
+enum Found {
+    Bounds((u64, u64)),
+    Optimum(u64),
+}
+
+fn approximate_bounds<T: Convoluter>(
+    prepared: &PreparedInput,
+    epsilon: Epsilon,
+    context: &Context,
+) -> Result<(u64, u64), Interrupted> {
+    Ok(prepared
+        .approximate_with_context::<T>(epsilon, context)?
+        .partition_bounds())
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        fft::{FFT, NTT},
+        helpers::dynamic_programing_partition,
+    };
+
+    use super::*;
+
+    fn verify_portfolio<T: Convoluter + 'static>(
+        input: &[u64],
+        portfolio: Portfolio<T>,
+        optimal: bool,
+    ) {
+        let opt = dynamic_programing_partition(input).unwrap();
+        let result = portfolio.solve(input).unwrap();
+        assert!(result.lower_bound <= opt && opt <= result.upper_bound);
+        assert!(result.value <= result.lower_bound);
+        if let Some(witness) = result.witness {
+            assert_eq!(witness.iter().map(|&i| input[i]).sum::<u64>(), result.value);
+        }
+        if optimal {
+            assert_eq!((result.value, result.upper_bound), (opt, opt));
+        }
+    }
+
+    #[test]
+    fn test_portfolio() {
+        let input = (0..300).map(|x| 1000 + x * x % 997).collect::<Vec<u64>>();
+        verify_portfolio(
+            &input,
+            Portfolio::<FFT>::new(Duration::from_secs(600)),
+            true,
+        );
+        verify_portfolio(&input, Portfolio::<NTT>::new(Duration::ZERO), false);
+        let mut approximate_only = Portfolio::<NTT>::new(Duration::from_secs(600));
+        approximate_only.exact_limit = 0;
+        approximate_only.epsilons = vec![Epsilon::from_inverse(2)];
+        verify_portfolio(&input, approximate_only, false);
+        let mut coarse = Portfolio::<FFT>::new(Duration::from_secs(600));
+        coarse.exact_limit = 0;
+        coarse.epsilons = vec![Epsilon::from_inverse(2)];
+        verify_portfolio(
+            &[58795, 13966, 41074, 32670, 20202, 20658, 50218],
+            coarse,
+            false,
+        );
+        verify_portfolio(&[], Portfolio::<FFT>::new(Duration::ZERO), true);
+    }
+
+    #[test]
+    fn test_portfolio_deadline() {
+        // Few distinct values with many copies take the multiset path, which needs far longer
+        // than the deadline for this epsilon. All sums are even while half of the total is odd,
+        // so the bounds never meet and only the deadline ends the race.
+        let input = (0..20)
+            .flat_map(|v| [60000 + 40 * v; 5000])
+            .chain([2])
+            .collect::<Vec<u64>>();
+        let mut portfolio = Portfolio::<FFT>::new(Duration::from_millis(200));
+        portfolio.exact_limit = 0;
+        portfolio.epsilons = vec![Epsilon::from_inverse(1000)];
+        let start = Instant::now();
+        let result = portfolio.solve(&input).unwrap();
+        assert!(
+            start.elapsed() < Duration::from_secs(2),
+            "{:?}",
+            start.elapsed()
+        );
+        assert!(result.lower_bound < result.upper_bound);
+    }
+}
@@ -0,0 +1,221 @@
+use crate::{
+    helpers::gcd,
+    solver::{PartitionSolver, Solution, SolverError, SolverInfo},
+};
+
+/// A reduction applied by [`preprocess`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Rule {
+    /// `count` items of weight zero were dropped; they fit on either side.
+    ZeroItems { count: usize },
+    /// Every weight was divided by their common divisor `gcd`, which divides every subset sum.
+    CommonDivisor { gcd: u64 },
+    /// The item at `index` weighs at least as much as all the others together, so the optimum is
+    /// the rest of the items.
+    DominantItem { index: usize },
+}
+
+/// An input after [`preprocess`], which maps solutions of the reduced input back to the original.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Preprocessed {
+    values: Vec<u64>,
+    // The original index of every reduced item.
+    indices: Vec<usize>,
+    gcd: u64,
+    rules: Vec<Rule>,
+    trivial: Option<Solution>,
+}
+
+/// Reduces a partition instance: drops zero weights, then either solves it outright when one item
+/// dominates the rest, or divides out the common divisor of the weights. No rule loses
+/// solutions, and [`Preprocessed::restore`] maps a solution of the reduced input to one of
+/// `input` of the same quality.
+pub fn preprocess(input: &[u64]) -> Preprocessed {
+    let mut rules = Vec::new();
+    let indices = (0..input.len())
+        .filter(|&i| input[i] != 0)
+        .collect::<Vec<_>>();
+    if indices.len() < input.len() {
+        rules.push(Rule::ZeroItems {
+            count: input.len() - indices.len(),
+        });
+    }
+    let sigma: u128 = indices.iter().map(|&i| input[i] as u128).sum();
+    let heaviest = indices.iter().copied().max_by_key(|&i| input[i]);
+    if let Some(index) = heaviest.filter(|&i| 2 * input[i] as u128 >= sigma) {
+        rules.push(Rule::DominantItem { index });
+        let rest = indices.iter().copied().filter(|&i| i != index).collect();
+        let value = (sigma - input[index] as u128) as u64;
+        return Preprocessed {
+            values: Vec::new(),
+            indices: Vec::new(),
+            gcd: 1,
+            rules,
+            trivial: Some(Solution {
+                value,
+                witness: Some(rest),
+                lower_bound: value,
+                upper_bound: value,
+            }),
+        };
+    }
+    if indices.is_empty() {
+        return Preprocessed {
+            values: Vec::new(),
+            indices,
+            gcd: 1,
+            rules,
+            trivial: Some(Solution {
+                value: 0,
+                witness: Some(Vec::new()),
+                lower_bound: 0,
+                upper_bound: 0,
+            }),
+        };
+    }
+    let gcd = indices.iter().fold(0, |g, &i| gcd(g, input[i]));
+    if gcd > 1 {
+        rules.push(Rule::CommonDivisor { gcd });
+    }
+    Preprocessed {
+        values: indices.iter().map(|&i| input[i] / gcd).collect(),
+        indices,
+        gcd,
+        rules,
+        trivial: None,
+    }
+}
+
+impl Preprocessed {
+    /// The reduced weights, empty when the instance was solved outright.
+    pub fn values(&self) -> &[u64] {
+        &self.values
+    }
+    /// The rules that fired, in the order they were applied.
+    pub fn rules(&self) -> &[Rule] {
+        &self.rules
+    }
+    /// The common divisor that was divided out, 1 if none was.
+    pub fn gcd(&self) -> u64 {
+        self.gcd
+    }
+    /// The original index of the reduced item at `index`.
+    pub fn original_index(&self, index: usize) -> usize {
+        self.indices[index]
+    }
+    /// The optimal solution of the original input, when a rule found it.
+    pub fn trivial(&self) -> Option<&Solution> {
+        self.trivial.as_ref()
+    }
+    /// Maps a solution of the reduced input to one of the original input.
+    pub fn restore(&self, solution: Solution) -> Solution {
+        Solution {
+            value: solution.value * self.gcd,
+            witness: solution
+                .witness
+                .map(|witness| witness.into_iter().map(|i| self.indices[i]).collect()),
+            lower_bound: solution.lower_bound * self.gcd,
+            upper_bound: solution.upper_bound * self.gcd,
+        }
+    }
+}
+
+/// Runs [`preprocess`] before `solver`, answering trivial instances without it and mapping its
+/// solutions and errors back to the original input.
+pub struct Preprocessing<S: PartitionSolver> {
+    pub solver: S,
+}
+
+impl<S: PartitionSolver> PartitionSolver for Preprocessing<S> {
+    fn info(&self) -> SolverInfo {
+        self.solver.info()
+    }
+    fn partition(&self, input: &[u64]) -> Result<Solution, SolverError> {
+        let preprocessed = preprocess(input);
+        if let Some(solution) = preprocessed.trivial() {
+            return Ok(solution.clone());
+        }
+        match self.solver.partition(preprocessed.values()) {
+            Ok(solution) => Ok(preprocessed.restore(solution)),
+            Err(SolverError::WeightTooLarge { index, max, .. }) => {
+                let index = preprocessed.original_index(index);
+                Err(SolverError::WeightTooLarge {
+                    index,
+                    weight: input[index],
+                    max: max.saturating_mul(preprocessed.gcd()),
+                })
+            }
+            Err(error) => Err(error),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        meet_in_the_middle::exact_partition,
+        solver::{DynamicProgramingSolver, MeetInTheMiddleSolver, PartitionSolver, SolverError},
+    };
+
+    use super::{preprocess, Preprocessing, Rule};
+
+    #[test]
+    fn test_preprocess() {
+        let preprocessed = preprocess(&[0, 6, 9, 0, 12, 3]);
+        assert_eq!(
+            preprocessed.rules(),
+            [Rule::ZeroItems { count: 2 }, Rule::CommonDivisor { gcd: 3 }]
+        );
+        assert_eq!(preprocessed.values(), [2, 3, 4, 1]);
+        assert!(preprocessed.trivial().is_none());
+
+        let preprocessed = preprocess(&[3, 50, 0, 20, 7]);
+        assert_eq!(
+            preprocessed.rules(),
+            [
+                Rule::ZeroItems { count: 1 },
+                Rule::DominantItem { index: 1 }
+            ]
+        );
+        let solution = preprocessed.trivial().unwrap();
+        assert_eq!(solution.value, 30);
+        assert_eq!(solution.witness, Some(vec![0, 3, 4]));
+
+        assert_eq!(preprocess(&[5, 7, 9]).rules(), []);
+        assert_eq!(preprocess(&[0, 0]).trivial().unwrap().value, 0);
+    }
+
+    #[test]
+    fn test_preprocessing_solver() {
+        let inputs: [&[u64]; 4] = [
+            &[0, 10, 20, 30, 40, 70, 0, 90],
+            &[1000, 1, 2, 3],
+            &[6, 6, 6, 12, 18, 24, 30],
+            &[],
+        ];
+        for input in inputs {
+            let opt = exact_partition(input).unwrap().sum as u64;
+            let solver = Preprocessing {
+                solver: MeetInTheMiddleSolver,
+            };
+            let solution = solver.partition(input).unwrap();
+            assert_eq!(solution.value, opt, "{input:?}");
+            let witness = solution.witness.unwrap();
+            assert_eq!(witness.iter().map(|&i| input[i]).sum::<u64>(), opt);
+            let solver = Preprocessing {
+                solver: DynamicProgramingSolver,
+            };
+            assert_eq!(solver.partition(input).unwrap().value, opt);
+        }
+        let input = [0; 60].into_iter().chain([4, 6]).collect::<Vec<_>>();
+        assert!(MeetInTheMiddleSolver.partition(&input).is_err());
+        let solver = Preprocessing {
+            solver: MeetInTheMiddleSolver,
+        };
+        assert_eq!(solver.partition(&input).unwrap().value, 4);
+        assert_eq!(
+            solver.partition(&[3; 60]),
+            Err(SolverError::TooManyItems { items: 60, max: 40 })
+        );
+    }
+}
@@ -0,0 +1,650 @@
+use std::{
+    fmt::{self, Display},
+    marker::PhantomData,
+    time::{Duration, Instant},
+};
+
+use crate::{
+    approximation::{ApproximateSumset, Epsilon, Side},
+    context::Interrupted,
+    dynamic_programing::bitset_partition,
+    exact_is_cheaper,
+    fft::Convoluter,
+    fptas::{gens_levner_subset_sum, kellerer_subset_sum},
+    helpers::naive_sumset,
+    karmarkar_karp::{complete_karmarkar_karp, karmarkar_karp, SearchLimits},
+    meet_in_the_middle::exact_partition,
+    split::{approximate_ratio_split, Ratio},
+};
+
+/// How a solver's answer relates to the optimum.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SolverKind {
+    Exact,
+    Approximate,
+    Heuristic,
+}
+
+/// The guarantee a solver gives on its value.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Guarantee {
+    /// The value is the optimum.
+    Optimal,
+    /// The value is within `epsilon * sigma / 2` of the optimum.
+    Additive { epsilon: Epsilon },
+    /// The value is an achievable sum, but can be arbitrarily far from the optimum.
+    None,
+}
+
+/// Describes a [`PartitionSolver`].
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct SolverInfo {
+    pub name: &'static str,
+    pub kind: SolverKind,
+    pub guarantee: Guarantee,
+}
+
+/// What a solver found, before timing.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Solution {
+    /// The sum of the side not exceeding half of the total.
+    pub value: u64,
+    /// Indices of the items on that side, if the solver can tell them.
+    pub witness: Option<Vec<usize>>,
+    /// A certified lower bound on the optimum.
+    pub lower_bound: u64,
+    /// A certified upper bound on the optimum.
+    pub upper_bound: u64,
+}
+
+/// A [`Solution`] together with the time it took.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PartitionResult {
+    pub value: u64,
+    pub witness: Option<Vec<usize>>,
+    pub lower_bound: u64,
+    pub upper_bound: u64,
+    pub elapsed: Duration,
+}
+
+/// Why a solver could not run on an input.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SolverError {
+    /// The solver only supports weights up to `max`.
+    WeightTooLarge { index: usize, weight: u64, max: u64 },
+    /// The solver only supports up to `max` items.
+    TooManyItems { items: usize, max: usize },
+    /// The total does not fit in a `u64`.
+    SumOverflow,
+    /// A table over the sums up to `target` is larger than
+    /// [`MAX_TABLE_TARGET`](crate::dynamic_programing::MAX_TABLE_TARGET) or does not fit in memory.
+    TableTooLarge { target: u64 },
+    /// The solver was cancelled or ran past its deadline.
+    Interrupted(Interrupted),
+}
+
+impl From<Interrupted> for SolverError {
+    fn from(interrupted: Interrupted) -> Self {
+        SolverError::Interrupted(interrupted)
+    }
+}
+
+impl Display for SolverError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SolverError::WeightTooLarge { index, weight, max } => {
+                write!(
+                    f,
+                    "item {index} weighs {weight}, more than the supported {max}"
+                )
+            }
+            SolverError::TooManyItems { items, max } => {
+                write!(f, "{items} items given, at most {max} are supported")
+            }
+            SolverError::SumOverflow => write!(f, "the total weight overflows u64"),
+            SolverError::TableTooLarge { target } => {
+                write!(
+                    f,
+                    "a table of the sums up to {target} does not fit in memory"
+                )
+            }
+            SolverError::Interrupted(interrupted) => interrupted.fmt(f),
+        }
+    }
+}
+
+impl std::error::Error for SolverError {}
+
+/// A method for the partition problem: finding the largest subset sum not exceeding half of the
+/// total.
+pub trait PartitionSolver {
+    fn info(&self) -> SolverInfo;
+
+    /// Solves `input` without timing, see [`PartitionSolver::solve`].
+    fn partition(&self, input: &[u64]) -> Result<Solution, SolverError>;
+
+    fn solve(&self, input: &[u64]) -> Result<PartitionResult, SolverError> {
+        let start = Instant::now();
+        let solution = self.partition(input)?;
+        Ok(PartitionResult {
+            value: solution.value,
+            witness: solution.witness,
+            lower_bound: solution.lower_bound,
+            upper_bound: solution.upper_bound,
+            elapsed: start.elapsed(),
+        })
+    }
+}
+
+fn half_sum(input: &[u64]) -> Result<u64, SolverError> {
+    input
+        .iter()
+        .try_fold(0u64, |sum, &x| sum.checked_add(x))
+        .map(|sigma| sigma / 2)
+        .ok_or(SolverError::SumOverflow)
+}
+
+fn as_u16(input: &[u64]) -> Result<Vec<u16>, SolverError> {
+    input
+        .iter()
+        .enumerate()
+        .map(|(index, &weight)| {
+            u16::try_from(weight).map_err(|_| SolverError::WeightTooLarge {
+                index,
+                weight,
+                max: u16::MAX as u64,
+            })
+        })
+        .collect()
+}
+
+fn exact(value: u64, witness: Option<Vec<usize>>) -> Solution {
+    Solution {
+        value,
+        witness,
+        lower_bound: value,
+        upper_bound: value,
+    }
+}
+
+/// The intersection of two certified intervals `(lower_bound, upper_bound)` around the same
+/// optimum, or `None` if they contradict each other.
+///
+/// Certified bounds can only contradict through a bug in one of the methods, which is caught in
+/// debug builds. Combining them anyway would report an empty interval as certified, so callers
+/// keep the bounds they had instead.
+pub(crate) fn merge_bounds(current: (u64, u64), found: (u64, u64)) -> Option<(u64, u64)> {
+    let consistent = found.0 <= found.1 && found.0 <= current.1 && current.0 <= found.1;
+    debug_assert!(consistent, "{found:?} contradicts {current:?}");
+    consistent.then(|| (current.0.max(found.0), current.1.min(found.1)))
+}
+
+// An approximation within `epsilon * t` whose value is an achievable sum.
+fn approximate(value: u64, witness: Option<Vec<usize>>, t: u64, epsilon: Epsilon) -> Solution {
+    Solution {
+        value,
+        witness,
+        lower_bound: value,
+        upper_bound: t.min(value + epsilon.ceil_mul(t, 1)),
+    }
+}
+
+/// The sumset approximation of [`approximate_partition`](crate::approximate_partition) behind the [`PartitionSolver`]
+/// interface. Weights must fit in `u16`.
+///
+/// The approximate sums are not achievable themselves, so they only certify the bounds. The value
+/// is the [`karmarkar_karp`] sum, with its items, and says nothing about the approximation: it
+/// may fall below the certified lower bound, which is the larger of that sum and the certified
+/// one. The elapsed time includes the heuristic as well.
+pub struct ApproximateSolver<T: Convoluter> {
+    pub epsilon: Epsilon,
+    _phantom: PhantomData<T>,
+}
+
+impl<T: Convoluter> ApproximateSolver<T> {
+    pub fn new(epsilon: impl Into<Epsilon>) -> Self {
+        Self {
+            epsilon: epsilon.into(),
+            _phantom: PhantomData,
+        }
+    }
+}
+
+impl<T: Convoluter> PartitionSolver for ApproximateSolver<T> {
+    fn info(&self) -> SolverInfo {
+        SolverInfo {
+            name: "approximate_partition",
+            kind: SolverKind::Approximate,
+            guarantee: Guarantee::None,
+        }
+    }
+    fn partition(&self, input: &[u64]) -> Result<Solution, SolverError> {
+        half_sum(input)?;
+        let (lower_bound, upper_bound) =
+            ApproximateSumset::new::<T>(&as_u16(input)?, self.epsilon).partition_bounds();
+        let heuristic = karmarkar_karp(input);
+        let value = heuristic.sum as u64;
+        // An achievable sum above the certified upper bound means the bounds are wrong.
+        debug_assert!(
+            value <= upper_bound,
+            "{value} exceeds the upper bound {upper_bound}"
+        );
+        Ok(Solution {
+            value,
+            witness: Some(heuristic.items),
+            lower_bound: lower_bound.max(value),
+            upper_bound,
+        })
+    }
+}
+
+/// [`approximate_ratio_split`] with ratio 1/2, which also tells the items. Weights must fit in
+/// `u16`.
+pub struct WitnessSolver<T: Convoluter> {
+    pub epsilon: Epsilon,
+    _phantom: PhantomData<T>,
+}
+
+impl<T: Convoluter> WitnessSolver<T> {
+    pub fn new(epsilon: impl Into<Epsilon>) -> Self {
+        Self {
+            epsilon: epsilon.into(),
+            _phantom: PhantomData,
+        }
+    }
+}
+
+impl<T: Convoluter> PartitionSolver for WitnessSolver<T> {
+    fn info(&self) -> SolverInfo {
+        SolverInfo {
+            name: "approximate_ratio_split",
+            kind: SolverKind::Approximate,
+            guarantee: Guarantee::Additive {
+                epsilon: self.epsilon,
+            },
+        }
+    }
+    fn partition(&self, input: &[u64]) -> Result<Solution, SolverError> {
+        let t = half_sum(input)?;
+        let split = approximate_ratio_split::<T>(
+            &as_u16(input)?,
+            Ratio::new(1, 2),
+            self.epsilon,
+            Side::Below,
+        )
+        .expect("1/2 is a valid ratio");
+        Ok(approximate(
+            split.first_sum,
+            Some(split.first),
+            t,
+            self.epsilon,
+        ))
+    }
+}
+
+/// Picks a method by the input, like [`approximate_partition`](crate::approximate_partition)
+/// but for any `u64` weights. Inputs with so few items that their `2^(n/2)` half-sums are no
+/// more than the sums an approximation may hold are solved exactly by [`exact_partition`], which
+/// does not care how large the weights are. Otherwise weights fitting in `u16` go through the
+/// [`WitnessSolver`] and larger ones through the [`KellererSolver`], and the [`karmarkar_karp`]
+/// partition is returned instead when it is better.
+pub struct DispatchSolver<T: Convoluter> {
+    pub epsilon: Epsilon,
+    _phantom: PhantomData<T>,
+}
+
+impl<T: Convoluter> DispatchSolver<T> {
+    pub fn new(epsilon: impl Into<Epsilon>) -> Self {
+        Self {
+            epsilon: epsilon.into(),
+            _phantom: PhantomData,
+        }
+    }
+}
+
+impl<T: Convoluter> PartitionSolver for DispatchSolver<T> {
+    fn info(&self) -> SolverInfo {
+        SolverInfo {
+            name: "dispatch",
+            kind: SolverKind::Approximate,
+            guarantee: Guarantee::Additive {
+                epsilon: self.epsilon,
+            },
+        }
+    }
+    fn partition(&self, input: &[u64]) -> Result<Solution, SolverError> {
+        half_sum(input)?;
+        if exact_is_cheaper(input, self.epsilon) {
+            return MeetInTheMiddleSolver.partition(input);
+        }
+        let solution = if input.iter().all(|&x| x <= u16::MAX as u64) {
+            WitnessSolver::<T>::new(self.epsilon).partition(input)?
+        } else {
+            KellererSolver {
+                epsilon: self.epsilon,
+            }
+            .partition(input)?
+        };
+        let heuristic = karmarkar_karp(input);
+        if heuristic.sum as u64 <= solution.value {
+            return Ok(solution);
+        }
+        Ok(Solution {
+            value: heuristic.sum as u64,
+            witness: Some(heuristic.items),
+            lower_bound: heuristic.sum as u64,
+            ..solution
+        })
+    }
+}
+
+/// [`bitset_partition`], exact in `O(n * sigma / 64 * log n)` time and `sigma / 16` bytes, with
+/// the items.
+pub struct DynamicProgramingSolver;
+
+impl PartitionSolver for DynamicProgramingSolver {
+    fn info(&self) -> SolverInfo {
+        SolverInfo {
+            name: "dynamic_programing",
+            kind: SolverKind::Exact,
+            guarantee: Guarantee::Optimal,
+        }
+    }
+    fn partition(&self, input: &[u64]) -> Result<Solution, SolverError> {
+        let witness = bitset_partition(input)?;
+        Ok(exact(witness.sum as u64, Some(witness.items)))
+    }
+}
+
+/// Enumerates every subset with [`naive_sumset`], for at most 25 items.
+pub struct NaiveSolver;
+
+impl PartitionSolver for NaiveSolver {
+    fn info(&self) -> SolverInfo {
+        SolverInfo {
+            name: "naive",
+            kind: SolverKind::Exact,
+            guarantee: Guarantee::Optimal,
+        }
+    }
+    fn partition(&self, input: &[u64]) -> Result<Solution, SolverError> {
+        if input.len() > 25 {
+            return Err(SolverError::TooManyItems {
+                items: input.len(),
+                max: 25,
+            });
+        }
+        let t = half_sum(input)?;
+        let value = naive_sumset(input)
+            .into_iter()
+            .filter(|&x| x <= t)
+            .max()
+            .unwrap_or(0);
+        Ok(exact(value, None))
+    }
+}
+
+/// [`exact_partition`] by meet-in-the-middle, for at most
+/// [`MAX_ITEMS`](crate::meet_in_the_middle::MAX_ITEMS) items.
+pub struct MeetInTheMiddleSolver;
+
+impl PartitionSolver for MeetInTheMiddleSolver {
+    fn info(&self) -> SolverInfo {
+        SolverInfo {
+            name: "meet_in_the_middle",
+            kind: SolverKind::Exact,
+            guarantee: Guarantee::Optimal,
+        }
+    }
+    fn partition(&self, input: &[u64]) -> Result<Solution, SolverError> {
+        half_sum(input)?;
+        let witness = exact_partition(input)?;
+        Ok(exact(witness.sum as u64, Some(witness.items)))
+    }
+}
+
+/// The [`karmarkar_karp`] heuristic.
+pub struct KarmarkarKarpSolver;
+
+impl PartitionSolver for KarmarkarKarpSolver {
+    fn info(&self) -> SolverInfo {
+        SolverInfo {
+            name: "karmarkar_karp",
+            kind: SolverKind::Heuristic,
+            guarantee: Guarantee::None,
+        }
+    }
+    fn partition(&self, input: &[u64]) -> Result<Solution, SolverError> {
+        let t = half_sum(input)?;
+        let witness = karmarkar_karp(input);
+        Ok(Solution {
+            value: witness.sum as u64,
+            witness: Some(witness.items),
+            lower_bound: witness.sum as u64,
+            upper_bound: t,
+        })
+    }
+}
+
+/// [`complete_karmarkar_karp`], exact unless a limit stops it first. With limits it only
+/// promises the best partition found in time, and the upper bound is half of the total unless
+/// the search completed.
+pub struct CompleteKarmarkarKarpSolver {
+    pub limits: SearchLimits,
+}
+
+impl PartitionSolver for CompleteKarmarkarKarpSolver {
+    fn info(&self) -> SolverInfo {
+        let limited = self.limits.nodes.is_some() || self.limits.time.is_some();
+        SolverInfo {
+            name: "complete_karmarkar_karp",
+            kind: if limited {
+                SolverKind::Heuristic
+            } else {
+                SolverKind::Exact
+            },
+            guarantee: if limited {
+                Guarantee::None
+            } else {
+                Guarantee::Optimal
+            },
+        }
+    }
+    fn partition(&self, input: &[u64]) -> Result<Solution, SolverError> {
+        let t = half_sum(input)?;
+        let result = complete_karmarkar_karp(input, self.limits);
+        let value = result.witness.sum as u64;
+        Ok(Solution {
+            value,
+            witness: Some(result.witness.items),
+            lower_bound: value,
+            upper_bound: if result.optimal { value } else { t },
+        })
+    }
+}
+
+/// The [`gens_levner_subset_sum`] FPTAS.
+pub struct GensLevnerSolver {
+    pub epsilon: Epsilon,
+}
+
+impl PartitionSolver for GensLevnerSolver {
+    fn info(&self) -> SolverInfo {
+        SolverInfo {
+            name: "gens_levner",
+            kind: SolverKind::Approximate,
+            guarantee: Guarantee::Additive {
+                epsilon: self.epsilon,
+            },
+        }
+    }
+    fn partition(&self, input: &[u64]) -> Result<Solution, SolverError> {
+        let t = half_sum(input)?;
+        let value = gens_levner_subset_sum(input, t, self.epsilon);
+        Ok(approximate(value, None, t, self.epsilon))
+    }
+}
+
+/// The [`kellerer_subset_sum`] FPTAS.
+pub struct KellererSolver {
+    pub epsilon: Epsilon,
+}
+
+impl PartitionSolver for KellererSolver {
+    fn info(&self) -> SolverInfo {
+        SolverInfo {
+            name: "kellerer",
+            kind: SolverKind::Approximate,
+            guarantee: Guarantee::Additive {
+                epsilon: self.epsilon,
+            },
+        }
+    }
+    fn partition(&self, input: &[u64]) -> Result<Solution, SolverError> {
+        let t = half_sum(input)?;
+        let value = kellerer_subset_sum(input, t, self.epsilon);
+        Ok(approximate(value, None, t, self.epsilon))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        fft::{FFT, NTT},
+        helpers::dynamic_programing_partition,
+    };
+
+    use super::*;
+
+    fn solvers(epsilon: Epsilon) -> Vec<Box<dyn PartitionSolver>> {
+        vec![
+            Box::new(ApproximateSolver::<FFT>::new(epsilon)),
+            Box::new(ApproximateSolver::<NTT>::new(epsilon)),
+            Box::new(WitnessSolver::<FFT>::new(epsilon)),
+            Box::new(DispatchSolver::<FFT>::new(epsilon)),
+            Box::new(DynamicProgramingSolver),
+            Box::new(NaiveSolver),
+            Box::new(MeetInTheMiddleSolver),
+            Box::new(KarmarkarKarpSolver),
+            Box::new(CompleteKarmarkarKarpSolver {
+                limits: SearchLimits::default(),
+            }),
+            Box::new(CompleteKarmarkarKarpSolver {
+                limits: SearchLimits {
+                    nodes: Some(3),
+                    time: None,
+                },
+            }),
+            Box::new(GensLevnerSolver { epsilon }),
+            Box::new(KellererSolver { epsilon }),
+        ]
+    }
+
+    fn verify_solvers(input: &[u64], epsilon: f64) {
+        let epsilon = Epsilon::at_most(epsilon);
+        let opt = dynamic_programing_partition(input).unwrap();
+        let t = input.iter().sum::<u64>() / 2;
+        for solver in solvers(epsilon) {
+            let info = solver.info();
+            let result = solver.solve(input).unwrap();
+            assert!(result.value <= t, "{info:?}");
+            assert!(result.value <= result.lower_bound, "{info:?}");
+            assert!(
+                result.lower_bound <= opt && opt <= result.upper_bound,
+                "{info:?}"
+            );
+            if let Some(witness) = &result.witness {
+                assert_eq!(witness.iter().map(|&i| input[i]).sum::<u64>(), result.value);
+            }
+            match info.guarantee {
+                Guarantee::Optimal => assert_eq!(result.value, opt, "{info:?}"),
+                Guarantee::Additive { epsilon } => {
+                    assert!(opt - result.value <= epsilon.floor_mul(t, 1), "{info:?}")
+                }
+                Guarantee::None => {}
+            }
+        }
+    }
+
+    #[test]
+    fn test_solvers() {
+        verify_solvers(&[], 0.1);
+        verify_solvers(
+            &[1001, 2000, 3000, 4000, 5000, 6000, 7000, 1000, 1002, 5],
+            0.01,
+        );
+        verify_solvers(&[8, 7, 6, 5, 4], 0.1);
+        verify_solvers(&[58795, 13966, 41074, 32670, 20202, 20658, 50218], 0.5);
+    }
+
+    #[test]
+    fn test_dispatch_solver() {
+        // Listing the `2^20` half-sums of 40 huge weights beats holding `40 * 10^6` approximate
+        // sums, so they are solved exactly.
+        let input = (1..=40u64)
+            .map(|i| (1 << 60) / i + i * i)
+            .collect::<Vec<_>>();
+        let result = DispatchSolver::<FFT>::new(Epsilon::from_inverse(1_000_000))
+            .solve(&input)
+            .unwrap();
+        assert_eq!(result.value, exact_partition(&input).unwrap().sum as u64);
+        assert_eq!(result.lower_bound, result.upper_bound);
+        // Many huge weights fall back to an approximation that still tells the items.
+        let input = (1..=200u64)
+            .map(|i| (1 << 40) + i * 7919)
+            .collect::<Vec<_>>();
+        let t = input.iter().sum::<u64>() / 2;
+        let result = DispatchSolver::<FFT>::new(0.5).solve(&input).unwrap();
+        let witness = result.witness.unwrap();
+        assert_eq!(witness.iter().map(|&i| input[i]).sum::<u64>(), result.value);
+        assert!(result.value <= t && t - result.value <= t / 2);
+        assert!(result.value <= result.lower_bound && result.lower_bound <= result.upper_bound);
+    }
+
+    #[test]
+    fn test_solver_errors() {
+        let error = ApproximateSolver::<FFT>::new(0.1).solve(&[1, 70000]);
+        assert_eq!(
+            error,
+            Err(SolverError::WeightTooLarge {
+                index: 1,
+                weight: 70000,
+                max: u16::MAX as u64
+            })
+        );
+        assert_eq!(
+            KarmarkarKarpSolver.solve(&[u64::MAX, 1]),
+            Err(SolverError::SumOverflow)
+        );
+        assert_eq!(
+            NaiveSolver.solve(&[1; 30]),
+            Err(SolverError::TooManyItems { items: 30, max: 25 })
+        );
+    }
+
+    #[test]
+    fn test_limited_search_info() {
+        let limited = CompleteKarmarkarKarpSolver {
+            limits: SearchLimits {
+                nodes: Some(1),
+                time: None,
+            },
+        };
+        assert_eq!(limited.info().kind, SolverKind::Heuristic);
+        assert_eq!(limited.info().guarantee, Guarantee::None);
+        let input = [8, 7, 6, 5, 4];
+        assert_eq!(limited.solve(&input).unwrap().upper_bound, 15);
+    }
+
+    #[test]
+    fn test_merge_bounds() {
+        assert_eq!(merge_bounds((3, 10), (5, 12)), Some((5, 10)));
+        assert_eq!(merge_bounds((3, 10), (7, 7)), Some((7, 7)));
+    }
+
+    #[test]
+    #[cfg_attr(debug_assertions, should_panic(expected = "contradicts"))]
+    fn test_merge_contradicting_bounds() {
+        assert_eq!(merge_bounds((3, 10), (11, 12)), None);
+    }
+}
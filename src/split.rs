@@ -0,0 +1,417 @@
+use std::{
+    fmt::{self, Display},
+    str::FromStr,
+};
+
+use crate::{
+    approximation::{approximate_subset, Epsilon, Side},
+    fft::Convoluter,
+    helpers::gcd,
+};
+
+/// One of the two sides of a split.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Bin {
+    First,
+    Second,
+}
+
+/// An item carrying a user label, such as a job name, through the solver.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LabeledItem<L> {
+    pub label: L,
+    pub weight: u16,
+}
+
+/// A split of the input into two sides, given by item indices.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Split {
+    /// Indices of the items on the first side, in increasing order.
+    pub first: Vec<usize>,
+    /// Indices of the remaining items, in increasing order.
+    pub second: Vec<usize>,
+    pub first_sum: u64,
+    pub second_sum: u64,
+    /// The sum the first side aims for.
+    pub target: u64,
+    /// How much worse than the best first side respecting the pins this split may be, measured
+    /// as distance to `target`.
+    pub error: u64,
+}
+
+impl Split {
+    /// Puts the items at the increasing indices `first` on the first side and the rest on the
+    /// second.
+    pub fn from_first(input: &[u16], first: Vec<usize>, target: u64, error: u64) -> Self {
+        let mut second = vec![];
+        let mut picked = first.iter().peekable();
+        for i in 0..input.len() {
+            if picked.next_if_eq(&&i).is_none() {
+                second.push(i);
+            }
+        }
+        let sum = |indices: &[usize]| indices.iter().map(|&i| input[i] as u64).sum();
+        Split {
+            first_sum: sum(&first),
+            second_sum: sum(&second),
+            first,
+            second,
+            target,
+            error,
+        }
+    }
+}
+
+/// A split ratio `numerator / denominator` in lowest terms, so that the target is computed in
+/// exact integer arithmetic.
+///
+/// Parses from `p/q` or from a decimal like `0.3`, and converts from an `f64` through its shortest
+/// decimal representation, so `0.3` is exactly `3/10`. Decimals are cut after 18 digits.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Ratio {
+    numerator: u64,
+    denominator: u64,
+}
+
+impl Ratio {
+    pub fn new(numerator: u64, denominator: u64) -> Self {
+        assert!(denominator >= 1);
+        let divisor = gcd(numerator, denominator).max(1);
+        Self {
+            numerator: numerator / divisor,
+            denominator: denominator / divisor,
+        }
+    }
+    pub fn numerator(self) -> u64 {
+        self.numerator
+    }
+    pub fn denominator(self) -> u64 {
+        self.denominator
+    }
+    /// `floor(ratio * x)`.
+    pub fn floor_mul(self, x: u64) -> u64 {
+        (x as u128 * self.numerator as u128 / self.denominator as u128) as u64
+    }
+}
+
+impl Display for Ratio {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}/{}", self.numerator, self.denominator)
+    }
+}
+
+/// A string that is neither `p/q` nor a decimal fitting in a [`Ratio`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ParseRatioError(String);
+
+impl Display for ParseRatioError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "`{}` is not a ratio like `3/10` or `0.3`", self.0)
+    }
+}
+
+impl std::error::Error for ParseRatioError {}
+
+impl FromStr for Ratio {
+    type Err = ParseRatioError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let error = || ParseRatioError(s.to_string());
+        if let Some((numerator, denominator)) = s.split_once('/') {
+            let numerator = numerator.trim().parse().map_err(|_| error())?;
+            let denominator = denominator.trim().parse().map_err(|_| error())?;
+            return (denominator != 0)
+                .then(|| Ratio::new(numerator, denominator))
+                .ok_or_else(error);
+        }
+        let (whole, fraction) = s.trim().split_once('.').unwrap_or((s.trim(), ""));
+        let fraction = &fraction[..fraction.len().min(18)];
+        if whole.is_empty() && fraction.is_empty()
+            || !whole
+                .chars()
+                .chain(fraction.chars())
+                .all(|c| c.is_ascii_digit())
+        {
+            return Err(error());
+        }
+        let denominator = 10u64.pow(fraction.len() as u32);
+        let numerator = [whole, fraction]
+            .concat()
+            .chars()
+            .try_fold(0u64, |numerator, digit| {
+                numerator
+                    .checked_mul(10)?
+                    .checked_add(digit.to_digit(10).unwrap() as u64)
+            })
+            .ok_or_else(error)?;
+        Ok(Ratio::new(numerator, denominator))
+    }
+}
+
+impl From<f64> for Ratio {
+    /// Ratios too large for a `u64` become `u64::MAX`, which is out of range for a split anyway.
+    fn from(ratio: f64) -> Self {
+        assert!(ratio.is_finite() && ratio >= 0.0, "{ratio}");
+        // `-0.0` prints with its sign.
+        format!("{}", ratio.abs())
+            .parse()
+            .unwrap_or(Ratio::new(u64::MAX, 1))
+    }
+}
+
+/// Why a split request is invalid.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SplitError {
+    /// The ratio does not lie in `[0, 1]`.
+    RatioOutOfRange { ratio: Ratio },
+    /// A pin names an item that does not exist.
+    NoSuchItem { index: usize },
+    /// An item is pinned to both sides.
+    ConflictingPins { index: usize },
+}
+
+impl Display for SplitError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SplitError::RatioOutOfRange { ratio } => {
+                write!(f, "ratio {ratio} does not lie in [0, 1]")
+            }
+            SplitError::NoSuchItem { index } => write!(f, "pinned item {index} does not exist"),
+            SplitError::ConflictingPins { index } => {
+                write!(f, "item {index} is pinned to both sides")
+            }
+        }
+    }
+}
+
+impl std::error::Error for SplitError {}
+
+/// A [`Split`] of labeled items, listing the items on each side.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LabeledSplit<'a, L> {
+    pub first: Vec<&'a LabeledItem<L>>,
+    pub second: Vec<&'a LabeledItem<L>>,
+    pub split: Split,
+}
+
+/// Splits labeled items like [`approximate_pinned_split`] splits their weights.
+pub fn approximate_labeled_split<'a, T: Convoluter, L>(
+    items: &'a [LabeledItem<L>],
+    ratio: impl Into<Ratio>,
+    epsilon: impl Into<Epsilon>,
+    side: Side,
+    pins: &[(usize, Bin)],
+) -> Result<LabeledSplit<'a, L>, SplitError> {
+    let weights = items.iter().map(|item| item.weight).collect::<Vec<_>>();
+    let split = approximate_pinned_split::<T>(&weights, ratio, epsilon, side, pins)?;
+    Ok(LabeledSplit::new(items, split))
+}
+
+impl<'a, L> LabeledSplit<'a, L> {
+    /// Lists the items on each side of a split of their weights.
+    pub fn new(items: &'a [LabeledItem<L>], split: Split) -> Self {
+        LabeledSplit {
+            first: split.first.iter().map(|&i| &items[i]).collect(),
+            second: split.second.iter().map(|&i| &items[i]).collect(),
+            split,
+        }
+    }
+}
+
+/// Splits `input` so that the first side sums to roughly `ratio` of the total.
+///
+/// The target is `floor(ratio * sigma)`, computed exactly, see [`Ratio`]. With [`Side::Below`]
+/// the first side never exceeds the target, otherwise it may land on either side of it. In both
+/// cases its distance to the target is at most `epsilon * target` worse than the best possible.
+/// Fails for a ratio outside `[0, 1]`.
+pub fn approximate_ratio_split<T: Convoluter>(
+    input: &[u16],
+    ratio: impl Into<Ratio>,
+    epsilon: impl Into<Epsilon>,
+    side: Side,
+) -> Result<Split, SplitError> {
+    approximate_pinned_split::<T>(input, ratio, epsilon, side, &[])
+}
+
+/// Like [`approximate_ratio_split`], but every `(index, bin)` pin forces that item to a side.
+///
+/// Items pinned to the first side are folded into the target, and the free items are split
+/// against what is left of it with [`approximate_subset`], which merges them with the bounded
+/// mergers of the sumset approximation. The guarantee holds against the best split respecting
+/// the pins, with `error` measured on the remaining target. If the items pinned to the first
+/// side already exceed the target, no free item joins them. Pinning an item twice to the same
+/// side is allowed; pinning a missing item or one to both sides is an error.
+pub fn approximate_pinned_split<T: Convoluter>(
+    input: &[u16],
+    ratio: impl Into<Ratio>,
+    epsilon: impl Into<Epsilon>,
+    side: Side,
+    pins: &[(usize, Bin)],
+) -> Result<Split, SplitError> {
+    let (ratio, epsilon) = (ratio.into(), epsilon.into());
+    if ratio.numerator() > ratio.denominator() {
+        return Err(SplitError::RatioOutOfRange { ratio });
+    }
+    let mut bins = vec![None; input.len()];
+    for &(index, bin) in pins {
+        let pinned = bins
+            .get_mut(index)
+            .ok_or(SplitError::NoSuchItem { index })?;
+        if pinned.replace(bin).is_some_and(|old| old != bin) {
+            return Err(SplitError::ConflictingPins { index });
+        }
+    }
+    let sigma: u64 = input.iter().map(|&x| x as u64).sum();
+    let target = ratio.floor_mul(sigma);
+    let pinned: u64 = (0..input.len())
+        .filter(|&i| bins[i] == Some(Bin::First))
+        .map(|i| input[i] as u64)
+        .sum();
+    let free = (0..input.len())
+        .filter(|&i| bins[i].is_none())
+        .collect::<Vec<_>>();
+    let values = free.iter().map(|&i| input[i] as u64).collect::<Vec<_>>();
+    let remaining = target.saturating_sub(pinned);
+    let choice = approximate_subset::<T>(&values, remaining, epsilon, side);
+    let mut first = (0..input.len())
+        .filter(|&i| bins[i] == Some(Bin::First))
+        .chain(choice.picks.iter().map(|&j| free[j]))
+        .collect::<Vec<_>>();
+    first.sort_unstable();
+    let error = epsilon.floor_mul(remaining, 1);
+    Ok(Split::from_first(input, first, target, error))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{fft::FFT, helpers::naive_sumset};
+
+    fn verify_ratio_split(input: &[u16], ratio: Ratio, epsilon: f64, side: Side) {
+        let split = approximate_ratio_split::<FFT>(input, ratio, epsilon, side).unwrap();
+        let values = input.iter().map(|&x| x as u64).collect::<Vec<_>>();
+        let mut sums = naive_sumset(&values);
+        sums.push(0);
+        let best = match side {
+            Side::Below => {
+                split.target - sums.iter().filter(|&&s| s <= split.target).max().unwrap()
+            }
+            Side::Nearest => sums.iter().map(|s| s.abs_diff(split.target)).min().unwrap(),
+        };
+        if side == Side::Below {
+            assert!(split.first_sum <= split.target);
+        }
+        assert!(split.first_sum.abs_diff(split.target) <= best + split.error);
+        assert_eq!(split.first.len() + split.second.len(), input.len());
+        assert_eq!(
+            split.first_sum + split.second_sum,
+            values.iter().sum::<u64>()
+        );
+    }
+
+    #[test]
+    fn test_ratio_split() {
+        let input = [
+            1001, 2000, 3000, 4000, 5000, 6000, 7000, 1000, 1001, 1002, 1003, 5,
+        ];
+        for ratio in [0.0, 0.3, 0.5, 0.7, 1.0].map(Ratio::from) {
+            for epsilon in [0.1, 0.01] {
+                verify_ratio_split(&input, ratio, epsilon, Side::Below);
+                verify_ratio_split(&input, ratio, epsilon, Side::Nearest);
+            }
+        }
+    }
+
+    #[test]
+    fn test_pinned_split() {
+        let input = [
+            1001, 2000, 3000, 4000, 5000, 6000, 7000, 1000, 1001, 1002, 1003, 5,
+        ];
+        let pins = [
+            (6, Bin::First),
+            (5, Bin::Second),
+            (0, Bin::First),
+            (6, Bin::First),
+        ];
+        let free = [1, 2, 3, 4, 7, 8, 9, 10, 11];
+        let mut sums = naive_sumset(&free.map(|i| input[i] as u64));
+        sums.push(0);
+        for ratio in [0.1, 0.3, 0.5, 0.7] {
+            for side in [Side::Below, Side::Nearest] {
+                let split =
+                    approximate_pinned_split::<FFT>(&input, ratio, 0.01, side, &pins).unwrap();
+                assert!(split.first.contains(&6) && split.first.contains(&0));
+                assert!(split.second.contains(&5));
+                let offset = (input[0] + input[6]) as u64;
+                let best = sums
+                    .iter()
+                    .map(|s| s + offset)
+                    .filter(|&s| side == Side::Nearest || s <= split.target.max(offset))
+                    .map(|s| s.abs_diff(split.target))
+                    .min()
+                    .unwrap();
+                assert!(split.first_sum.abs_diff(split.target) <= best + split.error);
+            }
+        }
+    }
+
+    #[test]
+    fn test_labeled_split() {
+        let items = [("a", 30), ("b", 20), ("c", 50), ("d", 40)]
+            .map(|(label, weight)| LabeledItem { label, weight });
+        let split =
+            approximate_labeled_split::<FFT, _>(&items, 0.5, 0.01, Side::Below, &[(1, Bin::First)])
+                .unwrap();
+        let labels = |side: &[&LabeledItem<&'static str>]| {
+            side.iter().map(|item| item.label).collect::<Vec<_>>()
+        };
+        assert_eq!(labels(&split.first), ["b", "c"]);
+        assert_eq!(labels(&split.second), ["a", "d"]);
+        assert_eq!(split.split.first_sum, 70);
+    }
+
+    #[test]
+    fn test_split_errors() {
+        let input = [3, 5, 8];
+        let split = |ratio, pins: &[(usize, Bin)]| {
+            approximate_pinned_split::<FFT>(&input, ratio, 0.1, Side::Below, pins)
+        };
+        assert_eq!(
+            split(1.5, &[]),
+            Err(SplitError::RatioOutOfRange {
+                ratio: Ratio::new(3, 2)
+            })
+        );
+        assert_eq!(
+            split(0.5, &[(3, Bin::First)]),
+            Err(SplitError::NoSuchItem { index: 3 })
+        );
+        assert_eq!(
+            split(0.5, &[(1, Bin::First), (1, Bin::Second)]),
+            Err(SplitError::ConflictingPins { index: 1 })
+        );
+        assert!(split(0.5, &[(1, Bin::First), (1, Bin::First)]).is_ok());
+    }
+
+    #[test]
+    fn test_ratio() {
+        assert_eq!(Ratio::from(0.3), Ratio::new(3, 10));
+        assert_eq!(Ratio::from(0.5), Ratio::new(1, 2));
+        assert_eq!(Ratio::from(1.0), Ratio::new(1, 1));
+        assert_eq!(Ratio::from(0.0), Ratio::new(0, 1));
+        assert_eq!(Ratio::from(1e300), Ratio::new(u64::MAX, 1));
+        assert_eq!("2/6".parse(), Ok(Ratio::new(1, 3)));
+        assert_eq!(".25".parse(), Ok(Ratio::new(1, 4)));
+        assert_eq!("3".parse(), Ok(Ratio::new(3, 1)));
+        for invalid in ["", ".", "1/0", "-0.5", "0.5x", "a/2"] {
+            assert!(invalid.parse::<Ratio>().is_err(), "{invalid}");
+        }
+        assert_eq!(Ratio::new(3, 10).floor_mul(u64::MAX), 5534023222112865484);
+    }
+
+    #[test]
+    fn test_ratio_split_empty() {
+        let split = approximate_ratio_split::<FFT>(&[], 0.3, 0.1, Side::Nearest).unwrap();
+        assert_eq!((split.first_sum, split.second_sum), (0, 0));
+    }
+}
@@ -1,27 +1,82 @@
-use std::collections::HashSet;
+use std::{iter::Enumerate, vec};
 
 use crate::{
     characteristic::{Characteristic, CharacteristicTrait as _},
     fft::Convoluter,
+    helpers::count_multiplicities,
 };
 
+/// Every sum of an element of `a` and an element of `b`, sorted and without duplicates.
 pub fn subset_sum<T: Convoluter>(a: &[u64], b: &[u64]) -> Vec<u64> {
+    subset_sum_iter::<T>(a, b).collect()
+}
+
+/// Like [`subset_sum`], but yields the sums one at a time instead of collecting them. The
+/// convolution is still computed in full up front; only decoding its result is lazy.
+pub fn subset_sum_iter<T: Convoluter>(a: &[u64], b: &[u64]) -> SumsetIter {
     let a_size = *a.iter().max().unwrap_or(&0u64) as usize;
     let b_size = *b.iter().max().unwrap_or(&0u64) as usize;
     let size = a_size + b_size + 1;
-    bounded_subset_sum::<T>(a, b, size)
+    bounded_subset_sum_iter::<T>(a, b, size)
 }
 
+/// Like [`subset_sum`] for sums below `bound`, sorted and without duplicates.
 pub fn bounded_subset_sum<T: Convoluter>(a: &[u64], b: &[u64], bound: usize) -> Vec<u64> {
+    bounded_subset_sum_iter::<T>(a, b, bound).collect()
+}
+
+/// Like [`bounded_subset_sum`], but yields the sums one at a time instead of collecting them, see
+/// [`subset_sum_iter`].
+pub fn bounded_subset_sum_iter<T: Convoluter>(a: &[u64], b: &[u64], bound: usize) -> SumsetIter {
     if a.len() * b.len() < 1000 {
-        return naive_sumset_sum(a, b);
+        let mut sums = naive_sumset_sum(a, b);
+        sums.retain(|&x| (x as usize) < bound);
+        return SumsetIter(Sums::Sorted(sums.into_iter()));
+    }
+    // Elements from `bound` on only give sums beyond it. The rest are convolved at the full size
+    // of their sums, since a cyclic convolution of size `bound` would wrap larger sums around.
+    let below = |set: &[u64]| {
+        set.iter()
+            .copied()
+            .filter(|&x| (x as usize) < bound)
+            .collect::<Vec<_>>()
+    };
+    let (a, b) = (below(a), below(b));
+    let size = match (a.iter().max(), b.iter().max()) {
+        (Some(&a_max), Some(&b_max)) => (a_max + b_max) as usize + 1,
+        _ => return SumsetIter(Sums::Sorted(Vec::new().into_iter())),
+    };
+    let encoder = Characteristic::with_size_1d(size);
+    let mut characteristic =
+        T::new(size).convolute_characteristic_vecs(&encoder.encode(&a), &encoder.encode(&b));
+    characteristic.truncate(bound);
+    SumsetIter(Sums::Characteristic(characteristic.into_iter().enumerate()))
+}
+
+/// The sums of [`subset_sum_iter`] in increasing order. The sums are already known when the
+/// iterator is created: small inputs hold them in a list, and larger ones hold the full
+/// characteristic vector of the convolution, which is only scanned for the next sum as the
+/// iterator advances.
+pub struct SumsetIter(Sums);
+
+enum Sums {
+    Sorted(vec::IntoIter<u64>),
+    Characteristic(Enumerate<vec::IntoIter<bool>>),
+}
+
+impl Iterator for SumsetIter {
+    type Item = u64;
+    fn next(&mut self) -> Option<u64> {
+        match &mut self.0 {
+            Sums::Sorted(sums) => sums.next(),
+            Sums::Characteristic(characteristic) => characteristic
+                .find(|&(_, reachable)| reachable)
+                .map(|(sum, _)| sum as u64),
+        }
     }
-    let encoder = Characteristic::with_size_1d(bound);
-    let characteristic =
-        T::new(bound).convolute_characteristic_vecs(&encoder.encode(a), &encoder.encode(b));
-    encoder.decode(&characteristic)
 }
 
+/// Every sum of a pair in `a` and a pair in `b`, sorted and without duplicates.
 pub fn subset_sum_2d<T: Convoluter>(a: &[(u64, u64)], b: &[(u64, u64)]) -> Vec<(u64, u64)> {
     let (a_x_size, a_y_size) = a
         .iter()
@@ -36,70 +91,152 @@ pub fn subset_sum_2d<T: Convoluter>(a: &[(u64, u64)], b: &[(u64, u64)]) -> Vec<(
     bounded_subset_sum_2d::<T>(a, b, x_size, y_size)
 }
 
+/// Like [`subset_sum_2d`] for sums in an `x_size` by `y_size` grid.
 pub fn bounded_subset_sum_2d<T: Convoluter>(
     a: &[(u64, u64)],
     b: &[(u64, u64)],
     x_size: usize,
     y_size: usize,
 ) -> Vec<(u64, u64)> {
-    println!("2D");
+    let in_grid = |&(x, y): &(u64, u64)| (x as usize) < x_size && (y as usize) < y_size;
     if a.len() * b.len() < 1000 {
-        return naive_sumset_sum_2d(a, b);
+        let mut sums = naive_sumset_sum_2d(a, b);
+        sums.retain(in_grid);
+        return sums;
     }
+    // Points outside the grid only give sums outside it.
+    let (a, b) = (
+        a.iter().copied().filter(in_grid).collect::<Vec<_>>(),
+        b.iter().copied().filter(in_grid).collect::<Vec<_>>(),
+    );
+    let (a, b) = (a.as_slice(), b.as_slice());
     let encoder = Characteristic::with_size_2d(x_size, y_size);
-    let characteristic = T::new(encoder.fft_size())
+    let characteristic = T::new_2d(x_size, y_size)
         .convolute_characteristic_vecs(&encoder.encode(a), &encoder.encode(b));
     encoder.decode(&characteristic)
 }
 
-fn naive_sumset_sum(a: &[u64], b: &[u64]) -> Vec<u64> {
-    let mut result = HashSet::new();
-    for x in a {
-        for y in b {
-            result.insert(x + y);
+/// Computes every subset sum of a multiset. Each distinct value `v` with multiplicity `c`
+/// contributes exactly the progression `0, v, ..., c * v`, which [`bounded_copies_sumset`] builds
+/// from `O(log c)` items instead of every single copy.
+pub fn multiset_sumset<T: Convoluter>(input: &[u64]) -> Vec<u64> {
+    bounded_copies_sumset::<T>(&count_multiplicities(input).into_iter().collect::<Vec<_>>())
+}
+
+/// Computes every sum that uses each `value` at most `copies` times, for `(value, copies)` item
+/// types. The result is sorted. Its approximate counterpart is
+/// [`approximate_bounded_copies_sumset`](crate::approximation::approximate_bounded_copies_sumset).
+///
+/// The copies of a value are grouped into items of `1, 2, 4, ..., 2^(k-1)` copies and one item
+/// of the remaining `c - 2^k + 1 < 2^k` copies. Every count up to `c` is a sum of these groups, so
+/// merging their `O(log c)` items in the convolution tree yields exactly the progression.
+pub fn bounded_copies_sumset<T: Convoluter>(classes: &[(u64, usize)]) -> Vec<u64> {
+    let items = classes
+        .iter()
+        .filter(|&&(value, copies)| value != 0 && copies != 0)
+        .flat_map(|&(value, copies)| {
+            let copies = copies as u64;
+            let digits = 63 - (copies + 1).leading_zeros();
+            let rest = copies - ((1 << digits) - 1);
+            (0..digits)
+                .map(|i| 1 << i)
+                .chain([rest].into_iter().filter(|&rest| rest != 0))
+                .map(move |group| vec![0, group * value])
+        })
+        .collect::<Vec<_>>();
+    let mut result = merge_sumsets::<T>(&items);
+    result.sort();
+    result.dedup();
+    result
+}
+
+/// Computes every sum up to `t` that uses each value any number of times. Starting from the
+/// single values, the set of sums of at most `2^k` items is squared with itself, so
+/// `log(t / min)` convolutions suffice. The result is sorted.
+pub fn unbounded_sumset<T: Convoluter>(values: &[u64], t: u64) -> Vec<u64> {
+    let mut sums = values
+        .iter()
+        .copied()
+        .filter(|&x| x != 0 && x <= t)
+        .chain([0])
+        .collect::<Vec<_>>();
+    sums.sort();
+    sums.dedup();
+    loop {
+        let doubled = subset_sum_iter::<T>(&sums, &sums)
+            .take_while(|&x| x <= t)
+            .collect::<Vec<_>>();
+        if doubled == sums {
+            return sums;
         }
+        sums = doubled;
     }
-    result.into_iter().collect()
 }
 
-fn naive_sumset_sum_2d(a: &[(u64, u64)], b: &[(u64, u64)]) -> Vec<(u64, u64)> {
-    let mut result = HashSet::new();
-    for x in a {
-        for y in b {
-            result.insert((x.0 + y.0, x.1 + y.1));
+fn merge_sumsets<T: Convoluter>(sets: &[Vec<u64>]) -> Vec<u64> {
+    match sets {
+        [] => vec![0],
+        [set] => set.clone(),
+        _ => {
+            let (left, right) = sets.split_at(sets.len() / 2);
+            subset_sum::<T>(&merge_sumsets::<T>(left), &merge_sumsets::<T>(right))
         }
     }
-    result.into_iter().collect()
+}
+
+// Sorted like the decoded characteristic vectors, so both paths agree.
+fn naive_sumset_sum(a: &[u64], b: &[u64]) -> Vec<u64> {
+    let mut result = a
+        .iter()
+        .flat_map(|x| b.iter().map(move |y| x + y))
+        .collect::<Vec<_>>();
+    result.sort_unstable();
+    result.dedup();
+    result
+}
+
+fn naive_sumset_sum_2d(a: &[(u64, u64)], b: &[(u64, u64)]) -> Vec<(u64, u64)> {
+    let mut result = a
+        .iter()
+        .flat_map(|x| b.iter().map(move |y| (x.0 + y.0, x.1 + y.1)))
+        .collect::<Vec<_>>();
+    result.sort_unstable();
+    result.dedup();
+    result
 }
 
 #[cfg(test)]
 mod tests {
-    use std::collections::HashSet;
+    use std::collections::BTreeSet;
 
-    use crate::fft::{FFT, NTT};
+    use crate::{
+        fft::{FFT, NTT},
+        helpers::naive_sumset,
+    };
 
     use super::*;
 
     fn test_1d<T: Convoluter>(a: &[u64], b: &[u64]) {
-        let result = HashSet::from_iter(subset_sum::<T>(a, b));
-        let mut expected = HashSet::new();
+        let result = subset_sum::<T>(a, b);
+        let mut expected = BTreeSet::new();
         for i in a {
             for j in b {
                 expected.insert(i + j);
             }
         }
-        assert_eq!(result, expected);
+        assert_eq!(result, expected.into_iter().collect::<Vec<_>>());
+        assert_eq!(subset_sum_iter::<T>(a, b).collect::<Vec<_>>(), result);
     }
 
     fn test_2d<T: Convoluter>(a: &[(u64, u64)], b: &[(u64, u64)]) {
-        let result = HashSet::from_iter(subset_sum_2d::<T>(a, b));
-        let mut expected = HashSet::new();
+        let result = subset_sum_2d::<T>(a, b);
+        let mut expected = BTreeSet::new();
         for (i, j) in a {
             for (k, l) in b {
                 expected.insert((i + k, j + l));
             }
         }
-        assert_eq!(result, expected);
+        assert_eq!(result, expected.into_iter().collect::<Vec<_>>());
     }
 
     #[test]
@@ -151,4 +288,138 @@ mod tests {
             &(200..300).map(|a| (a, a + 600)).collect::<Vec<_>>(),
         )
     }
+
+    fn test_multiset<T: Convoluter>(input: &[u64]) {
+        let result = multiset_sumset::<T>(input);
+        let mut expected = naive_sumset(input);
+        expected.push(0);
+        expected.sort();
+        expected.dedup();
+        assert_eq!(result, expected);
+    }
+
+    fn test_unbounded<T: Convoluter>(values: &[u64], t: u64) {
+        let mut reachable = vec![false; t as usize + 1];
+        reachable[0] = true;
+        for x in 1..=t as usize {
+            reachable[x] = values
+                .iter()
+                .any(|&v| v != 0 && v as usize <= x && reachable[x - v as usize]);
+        }
+        let expected = (0..=t)
+            .filter(|&x| reachable[x as usize])
+            .collect::<Vec<_>>();
+        assert_eq!(unbounded_sumset::<T>(values, t), expected);
+    }
+
+    #[test]
+    fn test_unbounded_sumset_fft() {
+        test_unbounded::<FFT>(&[6, 10, 15], 200);
+        test_unbounded::<FFT>(&[0, 97, 1000], 5000);
+        test_unbounded::<FFT>(&[], 10);
+    }
+
+    #[test]
+    fn test_unbounded_sumset_ntt() {
+        test_unbounded::<NTT>(&[6, 10, 15], 200);
+        test_unbounded::<NTT>(&[0, 97, 1000], 5000);
+        test_unbounded::<NTT>(&[], 10);
+    }
+
+    #[test]
+    fn test_bounded_copies_sumset() {
+        assert_eq!(
+            bounded_copies_sumset::<FFT>(&[(3, 2), (5, 1)]),
+            [0, 3, 5, 6, 8, 11]
+        );
+        assert_eq!(
+            bounded_copies_sumset::<NTT>(&[(3, 2), (5, 1)]),
+            [0, 3, 5, 6, 8, 11]
+        );
+        assert_eq!(bounded_copies_sumset::<FFT>(&[(0, 4), (9, 0)]), [0]);
+        // Multiplicities just below, at and above powers of two.
+        for copies in [1, 3, 4, 7, 8, 100] {
+            let classes = [(1000, copies), (7, 5)];
+            let expected = (0..=copies as u64)
+                .flat_map(|i| (0..=5).map(move |j| i * 1000 + j * 7))
+                .collect::<Vec<_>>();
+            assert_eq!(bounded_copies_sumset::<FFT>(&classes), expected);
+        }
+    }
+
+    #[test]
+    fn test_multiset_sumset_fft() {
+        test_multiset::<FFT>(&[]);
+        test_multiset::<FFT>(&[0, 0]);
+        test_multiset::<FFT>(&[5, 5, 5, 5, 7, 7, 7, 100, 100, 100, 100, 100]);
+        test_multiset::<FFT>(&[1000, 1000, 1000, 999, 999, 999, 999, 17, 17, 17, 3, 3, 3, 3]);
+    }
+
+    #[test]
+    fn test_multiset_sumset_ntt() {
+        test_multiset::<NTT>(&[]);
+        test_multiset::<NTT>(&[0, 0]);
+        test_multiset::<NTT>(&[5, 5, 5, 5, 7, 7, 7, 100, 100, 100, 100, 100]);
+        test_multiset::<NTT>(&[1000, 1000, 1000, 999, 999, 999, 999, 17, 17, 17, 3, 3, 3, 3]);
+    }
+
+    fn test_bounded<T: Convoluter>(a: &[u64], b: &[u64], bound: usize) {
+        let expected = naive_sumset_sum(a, b)
+            .into_iter()
+            .filter(|&x| (x as usize) < bound)
+            .collect::<Vec<_>>();
+        assert_eq!(bounded_subset_sum::<T>(a, b, bound), expected);
+        assert!(bounded_subset_sum_iter::<T>(a, b, bound).eq(expected));
+    }
+
+    #[test]
+    fn test_bounded_subset_sum() {
+        // Small inputs are summed directly, larger ones by a convolution holding every sum, and
+        // both drop the sums from `bound` on.
+        test_bounded::<FFT>(&[0, 3, 7], &[0, 2, 9], 10);
+        test_bounded::<NTT>(&[0, 3, 7], &[0, 2, 9], 10);
+        test_bounded::<FFT>(&[5, 6], &[5, 6], 10);
+        let a = (0..40).map(|x| x * 3).collect::<Vec<_>>();
+        let b = (0..40).map(|x| x * 5).collect::<Vec<_>>();
+        test_bounded::<FFT>(&a, &b, 150);
+        test_bounded::<NTT>(&a, &b, 150);
+    }
+
+    fn test_2d_bounded<T: Convoluter>(
+        a: &[(u64, u64)],
+        b: &[(u64, u64)],
+        x_size: usize,
+        y_size: usize,
+    ) {
+        let result = bounded_subset_sum_2d::<T>(a, b, x_size, y_size);
+        let mut expected = BTreeSet::new();
+        for (i, j) in a {
+            for (k, l) in b {
+                if ((i + k) as usize) < x_size && ((j + l) as usize) < y_size {
+                    expected.insert((i + k, j + l));
+                }
+            }
+        }
+        assert_eq!(result, expected.into_iter().collect::<Vec<_>>());
+    }
+
+    fn test_2d_bounded_grids<T: Convoluter>() {
+        let a = (0..40).map(|a| (a % 4, 5 + a % 6)).collect::<Vec<_>>();
+        let b = (0..40).map(|a| (a % 3, 6 - a % 7)).collect::<Vec<_>>();
+        test_2d_bounded::<T>(&a, &b, 6, 17);
+        // Both the direct sums and the convolution drop the sums outside the grid.
+        let a = (0..40).map(|a| (a % 2 * 3, a / 2 % 2)).collect::<Vec<_>>();
+        test_2d_bounded::<T>(&a, &a, 4, 3);
+        test_2d_bounded::<T>(&a[..4], &a[..4], 4, 3);
+    }
+
+    #[test]
+    fn test_bounded_subset_sum_2d_fft() {
+        test_2d_bounded_grids::<FFT>();
+    }
+
+    #[test]
+    fn test_bounded_subset_sum_2d_ntt() {
+        test_2d_bounded_grids::<NTT>();
+    }
 }
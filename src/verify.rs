@@ -0,0 +1,346 @@
+use std::fmt::{self, Display};
+
+use crate::{approximation::Epsilon, solver::Solution};
+
+/// The elements that break a claimed approximation, see [`verify_approximation`].
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ApproximationReport {
+    /// Expected elements with no approximation close enough below them, in increasing order.
+    pub missing: Vec<u64>,
+    /// Approximate elements with no expected element close enough above them, in increasing
+    /// order.
+    pub spurious: Vec<u64>,
+}
+
+impl ApproximationReport {
+    pub fn is_valid(&self) -> bool {
+        self.missing.is_empty() && self.spurious.is_empty()
+    }
+}
+
+impl Display for ApproximationReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{} missing {:?}, {} spurious {:?}",
+            self.missing.len(),
+            self.missing,
+            self.spurious.len(),
+            self.spurious
+        )
+    }
+}
+
+// Whether `a` approximates `b`: `a <= b` and `(1 - delta_mul) * b <= a + delta_add`, with no
+// multiplicative slack for `None`. With `delta_mul = 1 / k` the second condition is
+// `(k - 1) * b <= k * (a + delta_add)`, which is checked exactly.
+fn approximates(a: u64, b: u64, delta_mul: Option<Epsilon>, delta_add: u64) -> bool {
+    let (b, slack) = (b as u128, a as u128 + delta_add as u128);
+    a as u128 <= b
+        && match delta_mul {
+            None => b <= slack,
+            Some(delta_mul) => {
+                let k = delta_mul.inverse() as u128;
+                (k - 1) * b <= k * slack
+            }
+        }
+}
+
+/// Whether some element of `approximation` approximates `element`, that is lies in
+/// `[(1 - delta_mul) * element - delta_add, element]`.
+pub fn is_approximated(
+    approximation: &[u64],
+    element: u64,
+    delta_mul: Option<Epsilon>,
+    delta_add: u64,
+) -> bool {
+    approximation
+        .iter()
+        .filter(|&&a| a <= element)
+        .max()
+        .is_some_and(|&a| approximates(a, element, delta_mul, delta_add))
+}
+
+/// Checks that `approximation` is a `(1 - delta_mul, delta_add)`-approximation of `expected`:
+/// every expected element `b` has an approximate element in
+/// `[(1 - delta_mul) * b - delta_add, b]`, and every approximate element has an expected element
+/// it approximates in that way. Neither input needs to be sorted.
+pub fn verify_approximation(
+    approximation: &[u64],
+    expected: &[u64],
+    delta_mul: Option<Epsilon>,
+    delta_add: u64,
+) -> ApproximationReport {
+    let mut approximation = approximation.to_vec();
+    approximation.sort_unstable();
+    approximation.dedup();
+    let mut expected = expected.to_vec();
+    expected.sort_unstable();
+    expected.dedup();
+    // The closest candidates are the largest approximation below and the smallest expected
+    // element above, and the condition only gets easier the closer they are.
+    let missing = expected
+        .iter()
+        .copied()
+        .filter(|&b| {
+            let below = approximation.partition_point(|&a| a <= b);
+            below == 0 || !approximates(approximation[below - 1], b, delta_mul, delta_add)
+        })
+        .collect();
+    let spurious = approximation
+        .iter()
+        .copied()
+        .filter(|&a| {
+            let above = expected.partition_point(|&b| b < a);
+            above == expected.len() || !approximates(a, expected[above], delta_mul, delta_add)
+        })
+        .collect();
+    ApproximationReport { missing, spurious }
+}
+
+/// A way in which a partition [`Solution`] is wrong, see [`verify_partition`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PartitionViolation {
+    /// The witness names an item that does not exist.
+    IndexOutOfRange { index: usize },
+    /// The witness names an item more than once.
+    DuplicateIndex { index: usize },
+    /// The witness items do not sum to the claimed value.
+    WitnessSum { witness_sum: u128, value: u64 },
+    /// The value is more than half of the total.
+    ExceedsHalf { value: u64, half: u128 },
+    /// The lower bound is above the upper bound.
+    EmptyBounds { lower_bound: u64, upper_bound: u64 },
+    /// The value is above the upper bound.
+    ValueAboveUpperBound { value: u64, upper_bound: u64 },
+    /// The value is above the reference optimum, so it cannot be achievable.
+    ValueAboveOptimum { value: u64, optimum: u64 },
+    /// The bounds do not contain the reference optimum.
+    OptimumOutOfBounds {
+        optimum: u64,
+        lower_bound: u64,
+        upper_bound: u64,
+    },
+}
+
+impl Display for PartitionViolation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            PartitionViolation::IndexOutOfRange { index } => {
+                write!(f, "witness item {index} does not exist")
+            }
+            PartitionViolation::DuplicateIndex { index } => {
+                write!(f, "witness item {index} appears more than once")
+            }
+            PartitionViolation::WitnessSum { witness_sum, value } => {
+                write!(f, "witness sums to {witness_sum}, not the value {value}")
+            }
+            PartitionViolation::ExceedsHalf { value, half } => {
+                write!(f, "value {value} exceeds half of the total {half}")
+            }
+            PartitionViolation::EmptyBounds {
+                lower_bound,
+                upper_bound,
+            } => write!(
+                f,
+                "lower bound {lower_bound} exceeds upper bound {upper_bound}"
+            ),
+            PartitionViolation::ValueAboveUpperBound { value, upper_bound } => {
+                write!(f, "value {value} exceeds the upper bound {upper_bound}")
+            }
+            PartitionViolation::ValueAboveOptimum { value, optimum } => {
+                write!(f, "value {value} exceeds the optimum {optimum}")
+            }
+            PartitionViolation::OptimumOutOfBounds {
+                optimum,
+                lower_bound,
+                upper_bound,
+            } => write!(
+                f,
+                "optimum {optimum} is outside the bounds [{lower_bound}, {upper_bound}]"
+            ),
+        }
+    }
+}
+
+/// Checks a partition [`Solution`] of `input`: its witness, if any, names distinct items summing
+/// to its value, the value is at most half of the total, and its bounds are consistent with the
+/// value at most the upper bound. The value may fall below the lower bound, which only has to
+/// bound the optimum. When a reference optimum is known, the value must not exceed it and the
+/// bounds must contain it. Returns every violation found.
+pub fn verify_partition(
+    input: &[u64],
+    solution: &Solution,
+    optimum: Option<u64>,
+) -> Vec<PartitionViolation> {
+    let mut violations = Vec::new();
+    if let Some(witness) = &solution.witness {
+        let mut seen = vec![false; input.len()];
+        let mut witness_sum = 0u128;
+        for &index in witness {
+            match seen.get_mut(index) {
+                None => violations.push(PartitionViolation::IndexOutOfRange { index }),
+                Some(true) => violations.push(PartitionViolation::DuplicateIndex { index }),
+                Some(seen) => {
+                    *seen = true;
+                    witness_sum += input[index] as u128;
+                }
+            }
+        }
+        if witness_sum != solution.value as u128 {
+            violations.push(PartitionViolation::WitnessSum {
+                witness_sum,
+                value: solution.value,
+            });
+        }
+    }
+    let half = input.iter().map(|&x| x as u128).sum::<u128>() / 2;
+    if solution.value as u128 > half {
+        violations.push(PartitionViolation::ExceedsHalf {
+            value: solution.value,
+            half,
+        });
+    }
+    let (lower_bound, upper_bound) = (solution.lower_bound, solution.upper_bound);
+    if lower_bound > upper_bound {
+        violations.push(PartitionViolation::EmptyBounds {
+            lower_bound,
+            upper_bound,
+        });
+    }
+    let value = solution.value;
+    if upper_bound < value {
+        violations.push(PartitionViolation::ValueAboveUpperBound { value, upper_bound });
+    }
+    if let Some(optimum) = optimum.filter(|&o| value > o) {
+        violations.push(PartitionViolation::ValueAboveOptimum { value, optimum });
+    }
+    if let Some(optimum) = optimum.filter(|&o| o < lower_bound || upper_bound < o) {
+        violations.push(PartitionViolation::OptimumOutOfBounds {
+            optimum,
+            lower_bound,
+            upper_bound,
+        });
+    }
+    violations
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use crate::{
+        fft::FFT,
+        portfolio::Portfolio,
+        solver::{PartitionSolver, Solution},
+    };
+
+    use super::*;
+
+    #[test]
+    fn test_verify_approximation() {
+        let tenth = Some(Epsilon::from_inverse(10));
+        let report = verify_approximation(&[10, 18, 30], &[10, 11, 20, 40], tenth, 0);
+        assert_eq!(report.missing, [40]);
+        assert_eq!(report.spurious, [30]);
+        assert!(!report.is_valid());
+        assert!(verify_approximation(&[18, 10], &[10, 11, 20, 11], tenth, 0).is_valid());
+        assert!(verify_approximation(&[], &[], None, 0).is_valid());
+        assert!(is_approximated(&[5, 17], 20, None, 3));
+        assert!(!is_approximated(&[5, 21], 20, None, 3));
+        // Exact even where `f64` cannot tell the values apart.
+        let large = 1 << 60;
+        assert!(is_approximated(&[large - large / 10], large, tenth, 0));
+        assert!(!is_approximated(&[large - large / 10 - 1], large, tenth, 0));
+    }
+
+    #[test]
+    fn test_verify_partition() {
+        let input = [3, 5, 8, 13];
+        let solution = Solution {
+            value: 13,
+            witness: Some(vec![3]),
+            lower_bound: 13,
+            upper_bound: 14,
+        };
+        assert_eq!(verify_partition(&input, &solution, Some(13)), []);
+        let solution = Solution {
+            value: 16,
+            witness: Some(vec![0, 0, 4, 1]),
+            lower_bound: 16,
+            upper_bound: 15,
+        };
+        assert_eq!(
+            verify_partition(&input, &solution, Some(14)),
+            [
+                PartitionViolation::DuplicateIndex { index: 0 },
+                PartitionViolation::IndexOutOfRange { index: 4 },
+                PartitionViolation::WitnessSum {
+                    witness_sum: 8,
+                    value: 16
+                },
+                PartitionViolation::ExceedsHalf {
+                    value: 16,
+                    half: 14
+                },
+                PartitionViolation::EmptyBounds {
+                    lower_bound: 16,
+                    upper_bound: 15
+                },
+                PartitionViolation::ValueAboveUpperBound {
+                    value: 16,
+                    upper_bound: 15
+                },
+                PartitionViolation::ValueAboveOptimum {
+                    value: 16,
+                    optimum: 14
+                },
+                PartitionViolation::OptimumOutOfBounds {
+                    optimum: 14,
+                    lower_bound: 16,
+                    upper_bound: 15
+                },
+            ]
+        );
+        let solution = Solution {
+            value: 20,
+            witness: None,
+            lower_bound: 13,
+            upper_bound: 13,
+        };
+        assert_eq!(
+            verify_partition(&[13, 30], &solution, Some(13)),
+            [
+                PartitionViolation::ValueAboveUpperBound {
+                    value: 20,
+                    upper_bound: 13
+                },
+                PartitionViolation::ValueAboveOptimum {
+                    value: 20,
+                    optimum: 13
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn test_verify_partition_value_below_lower_bound() {
+        // The witness is the best partition found, while the lower bound certifies a better one
+        // may exist, so a value below the lower bound is still valid.
+        let input = [
+            25350, 38001, 35606, 7469, 20344, 46768, 43237, 51806, 11931, 32917, 46612, 20348,
+        ];
+        let mut portfolio = Portfolio::<FFT>::new(Duration::from_secs(60));
+        portfolio.epsilons = vec![Epsilon::from_inverse(1000)];
+        portfolio.exact_limit = 0;
+        let solution = portfolio.partition(&input).unwrap();
+        assert_eq!(verify_partition(&input, &solution, Some(190034)), []);
+        let solution = Solution {
+            value: 189720,
+            witness: None,
+            lower_bound: 190033,
+            upper_bound: 190129,
+        };
+        assert_eq!(verify_partition(&input, &solution, Some(190034)), []);
+    }
+}